@@ -0,0 +1,81 @@
+-- Schema of the application as it was first deployed
+
+DO $$ BEGIN
+    CREATE TYPE user_gender AS ENUM ('MALE', 'FEMALE');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE user_role AS ENUM ('ADMIN', 'SALON_OWNER', 'CUSTOMER');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE general_status AS ENUM ('ACTIVATE', 'INACTIVATE');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE reservation_status AS ENUM ('WAITING', 'DONE', 'CANCEL');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS salons (
+    id BIGSERIAL PRIMARY KEY,
+    logo TEXT,
+    cover_photo TEXT,
+    name TEXT,
+    phone TEXT,
+    email TEXT,
+    description TEXT,
+    status general_status DEFAULT 'ACTIVATE',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id BIGSERIAL PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE,
+    password TEXT NOT NULL,
+    full_name TEXT,
+    email TEXT,
+    gender user_gender,
+    role user_role NOT NULL DEFAULT 'CUSTOMER',
+    avatar TEXT,
+    date_of_birth TIMESTAMPTZ,
+    salon_id BIGINT REFERENCES salons (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS salon_branches (
+    id BIGSERIAL PRIMARY KEY,
+    address TEXT,
+    salon_id BIGINT NOT NULL REFERENCES salons (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS therapies (
+    id BIGSERIAL PRIMARY KEY,
+    salon_id BIGINT NOT NULL REFERENCES salons (id) ON DELETE CASCADE,
+    name TEXT,
+    description TEXT,
+    price BIGINT,
+    duration TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS reservations (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    therapy_id BIGINT REFERENCES therapies (id) ON DELETE SET NULL,
+    salon_branch_id BIGINT REFERENCES salon_branches (id) ON DELETE SET NULL,
+    time_from TIMESTAMPTZ NOT NULL,
+    time_to TIMESTAMPTZ,
+    comment TEXT,
+    status reservation_status NOT NULL DEFAULT 'WAITING',
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS reservations_user_id_idx ON reservations (user_id);
+CREATE INDEX IF NOT EXISTS reservations_salon_branch_id_time_from_idx
+ON reservations (salon_branch_id, time_from);
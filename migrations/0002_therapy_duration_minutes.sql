@@ -0,0 +1,28 @@
+-- Therapy length is an interval in minutes, plus cleanup time blocked after it
+
+ALTER TABLE therapies ADD COLUMN IF NOT EXISTS duration_minutes INTEGER;
+ALTER TABLE therapies ADD COLUMN IF NOT EXISTS buffer_minutes INTEGER NOT NULL DEFAULT 0;
+
+-- NOTE: The old column kept the length as the time of day of a timestamp
+DO $$ BEGIN
+    IF EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'therapies' AND column_name = 'duration'
+    ) THEN
+        UPDATE therapies SET duration_minutes = NULLIF(
+            EXTRACT(HOUR FROM duration AT TIME ZONE 'UTC')::INTEGER * 60
+            + EXTRACT(MINUTE FROM duration AT TIME ZONE 'UTC')::INTEGER,
+            0
+        )
+        WHERE duration_minutes IS NULL
+        AND duration IS NOT NULL;
+    END IF;
+END $$;
+
+ALTER TABLE therapies DROP COLUMN IF EXISTS duration;
+
+ALTER TABLE therapies DROP CONSTRAINT IF EXISTS therapies_duration_minutes_check;
+ALTER TABLE therapies ADD CONSTRAINT therapies_duration_minutes_check
+CHECK (duration_minutes IS NULL OR duration_minutes > 0);
+ALTER TABLE therapies DROP CONSTRAINT IF EXISTS therapies_buffer_minutes_check;
+ALTER TABLE therapies ADD CONSTRAINT therapies_buffer_minutes_check CHECK (buffer_minutes >= 0);
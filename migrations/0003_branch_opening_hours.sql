@@ -0,0 +1,27 @@
+-- Weekly opening hours (local time of the branch, Monday = 0) and one-off closures
+-- of salon branches
+
+CREATE TABLE IF NOT EXISTS salon_branch_opening_hours (
+    id BIGSERIAL PRIMARY KEY,
+    salon_branch_id BIGINT NOT NULL REFERENCES salon_branches (id) ON DELETE CASCADE,
+    day_of_week SMALLINT NOT NULL CHECK (day_of_week BETWEEN 0 AND 6),
+    open_time TIME NOT NULL,
+    close_time TIME NOT NULL,
+    CHECK (open_time < close_time)
+);
+
+CREATE INDEX IF NOT EXISTS salon_branch_opening_hours_salon_branch_id_idx
+ON salon_branch_opening_hours (salon_branch_id);
+
+CREATE TABLE IF NOT EXISTS salon_branch_closures (
+    id BIGSERIAL PRIMARY KEY,
+    salon_branch_id BIGINT NOT NULL REFERENCES salon_branches (id) ON DELETE CASCADE,
+    time_from TIMESTAMPTZ NOT NULL,
+    time_to TIMESTAMPTZ NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK (time_from < time_to)
+);
+
+CREATE INDEX IF NOT EXISTS salon_branch_closures_salon_branch_id_idx
+ON salon_branch_closures (salon_branch_id, time_from);
@@ -0,0 +1,5 @@
+-- Minimum minutes before a reservation starts for customers to cancel or reschedule it
+
+ALTER TABLE salons
+ADD COLUMN IF NOT EXISTS cancellation_window_minutes INTEGER NOT NULL DEFAULT 0
+CHECK (cancellation_window_minutes >= 0);
@@ -0,0 +1,6 @@
+-- Statuses and cancel reason used by salon owners managing reservations
+
+ALTER TYPE reservation_status ADD VALUE IF NOT EXISTS 'CONFIRMED' AFTER 'WAITING';
+ALTER TYPE reservation_status ADD VALUE IF NOT EXISTS 'NO_SHOW' AFTER 'DONE';
+
+ALTER TABLE reservations ADD COLUMN IF NOT EXISTS cancel_reason TEXT;
@@ -0,0 +1,16 @@
+-- Every status change of a reservation, with who made it and why
+
+ALTER TYPE reservation_status ADD VALUE IF NOT EXISTS 'IN_PROGRESS' AFTER 'CONFIRMED';
+
+CREATE TABLE IF NOT EXISTS reservation_histories (
+    id BIGSERIAL PRIMARY KEY,
+    reservation_id BIGINT NOT NULL REFERENCES reservations (id) ON DELETE CASCADE,
+    from_status reservation_status,
+    to_status reservation_status NOT NULL,
+    actor_id BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS reservation_histories_reservation_id_idx
+ON reservation_histories (reservation_id);
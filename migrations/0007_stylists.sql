@@ -0,0 +1,37 @@
+-- Stylists work at one or more branches of a salon and serve reservations
+
+ALTER TYPE user_role ADD VALUE IF NOT EXISTS 'STYLIST';
+
+DO $$ BEGIN
+    CREATE TYPE invitation_status AS ENUM ('PENDING', 'ACCEPTED', 'DECLINED', 'REVOKED');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS stylist_branches (
+    stylist_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    salon_branch_id BIGINT NOT NULL REFERENCES salon_branches (id) ON DELETE CASCADE,
+    PRIMARY KEY (stylist_id, salon_branch_id)
+);
+
+CREATE INDEX IF NOT EXISTS stylist_branches_salon_branch_id_idx
+ON stylist_branches (salon_branch_id);
+
+CREATE TABLE IF NOT EXISTS stylist_invitations (
+    id BIGSERIAL PRIMARY KEY,
+    salon_id BIGINT NOT NULL REFERENCES salons (id) ON DELETE CASCADE,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    salon_branch_ids BIGINT[] NOT NULL DEFAULT '{}',
+    status invitation_status NOT NULL DEFAULT 'PENDING',
+    responded_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS stylist_invitations_pending_idx
+ON stylist_invitations (salon_id, user_id)
+WHERE status = 'PENDING';
+
+ALTER TABLE reservations
+ADD COLUMN IF NOT EXISTS stylist_id BIGINT REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS reservations_stylist_id_idx ON reservations (stylist_id);
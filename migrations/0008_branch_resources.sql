@@ -0,0 +1,34 @@
+-- Bookable resources (chairs, beds, ...) of a branch, needed by therapies and
+-- held by reservations
+
+DO $$ BEGIN
+    CREATE TYPE resource_type AS ENUM ('CHAIR', 'BED', 'WASH_STATION');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS branch_resources (
+    id BIGSERIAL PRIMARY KEY,
+    salon_branch_id BIGINT NOT NULL REFERENCES salon_branches (id) ON DELETE CASCADE,
+    resource_type resource_type NOT NULL,
+    name TEXT NOT NULL,
+    quantity INT NOT NULL CHECK (quantity > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS branch_resources_salon_branch_id_idx
+ON branch_resources (salon_branch_id);
+
+ALTER TABLE therapies
+ADD COLUMN IF NOT EXISTS resource_types resource_type[] NOT NULL DEFAULT '{}';
+
+-- `unit` is the 0-based index of the concrete chair, bed, ... among `quantity`
+CREATE TABLE IF NOT EXISTS reservation_resources (
+    reservation_id BIGINT NOT NULL REFERENCES reservations (id) ON DELETE CASCADE,
+    branch_resource_id BIGINT NOT NULL REFERENCES branch_resources (id) ON DELETE CASCADE,
+    unit INT NOT NULL CHECK (unit >= 0),
+    PRIMARY KEY (reservation_id, branch_resource_id, unit)
+);
+
+CREATE INDEX IF NOT EXISTS reservation_resources_branch_resource_id_idx
+ON reservation_resources (branch_resource_id);
@@ -0,0 +1,16 @@
+-- Failed sign-in counters per username and per IP, used to slow down and
+-- lock out password guessing
+
+DO $$ BEGIN
+    CREATE TYPE sign_in_scope AS ENUM ('USERNAME', 'IP');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS sign_in_throttles (
+    scope sign_in_scope NOT NULL,
+    key TEXT NOT NULL,
+    failed_count INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    locked_until TIMESTAMPTZ,
+    PRIMARY KEY (scope, key)
+);
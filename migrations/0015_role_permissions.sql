@@ -0,0 +1,35 @@
+-- Permissions granted to each role, checked per route instead of the role
+-- itself, so new roles only need their grants added here
+
+DO $$ BEGIN
+    CREATE TYPE permission AS ENUM (
+        'MANAGE_USERS',
+        'VIEW_ALL_RESERVATIONS',
+        'MANAGE_ALL_RESERVATIONS',
+        'MANAGE_SALON',
+        'MANAGE_SALON_STYLISTS',
+        'MANAGE_SALON_RESERVATIONS',
+        'VIEW_ASSIGNED_RESERVATIONS',
+        'BOOK_RESERVATIONS',
+        'RESPOND_STYLIST_INVITATIONS'
+    );
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS role_permissions (
+    role user_role NOT NULL,
+    permission permission NOT NULL,
+    PRIMARY KEY (role, permission)
+);
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('ADMIN', 'MANAGE_USERS'),
+    ('ADMIN', 'VIEW_ALL_RESERVATIONS'),
+    ('ADMIN', 'MANAGE_ALL_RESERVATIONS'),
+    ('SALON_OWNER', 'MANAGE_SALON'),
+    ('SALON_OWNER', 'MANAGE_SALON_STYLISTS'),
+    ('SALON_OWNER', 'MANAGE_SALON_RESERVATIONS'),
+    ('STYLIST', 'VIEW_ASSIGNED_RESERVATIONS'),
+    ('CUSTOMER', 'BOOK_RESERVATIONS'),
+    ('CUSTOMER', 'RESPOND_STYLIST_INVITATIONS')
+ON CONFLICT DO NOTHING;
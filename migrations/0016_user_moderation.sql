@@ -0,0 +1,6 @@
+-- Admins can suspend accounts and soft-delete them, deleted users keep their
+-- row so reservations and their history still refer to it
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_at TIMESTAMPTZ;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_reason TEXT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
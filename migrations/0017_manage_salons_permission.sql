@@ -0,0 +1,4 @@
+-- Permission for admins to approve, deactivate and reactivate salons, added on
+-- its own since a new enum value can't be used in the migration adding it
+
+ALTER TYPE permission ADD VALUE IF NOT EXISTS 'MANAGE_SALONS';
@@ -0,0 +1,39 @@
+-- New salons wait for an admin to approve them, only approved and active
+-- salons are listed publicly and take reservations
+
+DO $$ BEGIN
+    IF NOT EXISTS (
+        SELECT 1 FROM information_schema.columns
+        WHERE table_name = 'salons' AND column_name = 'approved_at'
+    ) THEN
+        ALTER TABLE salons ADD COLUMN approved_at TIMESTAMPTZ;
+        -- Salons from before the approval workflow count as approved
+        UPDATE salons SET approved_at = created_at;
+    END IF;
+END $$;
+
+ALTER TABLE salons ADD COLUMN IF NOT EXISTS status_reason TEXT;
+
+UPDATE salons SET status = 'ACTIVATE' WHERE status IS NULL;
+
+DO $$ BEGIN
+    CREATE TYPE salon_status_action AS ENUM ('APPROVE', 'DEACTIVATE', 'REACTIVATE');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS salon_status_histories (
+    id BIGSERIAL PRIMARY KEY,
+    salon_id BIGINT NOT NULL REFERENCES salons (id) ON DELETE CASCADE,
+    action salon_status_action NOT NULL,
+    actor_id BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS salon_status_histories_salon_id_idx
+ON salon_status_histories (salon_id);
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('ADMIN', 'MANAGE_SALONS')
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,4 @@
+-- Permission for customers to apply for a salon of their own, added on its
+-- own since a new enum value can't be used in the migration adding it
+
+ALTER TYPE permission ADD VALUE IF NOT EXISTS 'APPLY_FOR_SALON';
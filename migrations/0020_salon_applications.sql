@@ -0,0 +1,33 @@
+-- Customers apply for a salon, admins review the application and approving it
+-- creates the salon with its branches and makes the customer its owner
+
+DO $$ BEGIN
+    CREATE TYPE application_status AS ENUM ('PENDING', 'APPROVED', 'REJECTED');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS salon_applications (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    salon_name TEXT NOT NULL,
+    phone TEXT,
+    email TEXT,
+    description TEXT,
+    branch_addresses TEXT[] NOT NULL DEFAULT '{}',
+    notes TEXT,
+    status application_status NOT NULL DEFAULT 'PENDING',
+    review_comment TEXT,
+    reviewer_id BIGINT REFERENCES users (id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMPTZ,
+    salon_id BIGINT REFERENCES salons (id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- NOTE: One application under review per customer
+CREATE UNIQUE INDEX IF NOT EXISTS salon_applications_pending_user_id_idx
+ON salon_applications (user_id) WHERE status = 'PENDING';
+
+INSERT INTO role_permissions (role, permission) VALUES
+    ('CUSTOMER', 'APPLY_FOR_SALON')
+ON CONFLICT DO NOTHING;
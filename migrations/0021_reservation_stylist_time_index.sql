@@ -0,0 +1,3 @@
+-- Busy times of a stylist are looked up by start time like those of a branch
+
+CREATE INDEX IF NOT EXISTS reservations_stylist_id_time_from_idx ON reservations (stylist_id, time_from);
@@ -0,0 +1,4 @@
+-- Opening hours of a branch are local times of its IANA time zone, UTC for
+-- branches from before it could be set
+
+ALTER TABLE salon_branches ADD COLUMN IF NOT EXISTS time_zone TEXT NOT NULL DEFAULT 'UTC';
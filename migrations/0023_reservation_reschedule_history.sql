@@ -0,0 +1,17 @@
+-- Reschedules are recorded in the reservation history as their own action,
+-- with the start times the reservation moved between
+
+DO $$ BEGIN
+    CREATE TYPE reservation_history_action AS ENUM ('STATUS_CHANGE', 'RESCHEDULE');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END $$;
+
+ALTER TABLE reservation_histories
+ADD COLUMN IF NOT EXISTS action reservation_history_action NOT NULL DEFAULT 'STATUS_CHANGE';
+ALTER TABLE reservation_histories ADD COLUMN IF NOT EXISTS rescheduled_from TIMESTAMPTZ;
+ALTER TABLE reservation_histories ADD COLUMN IF NOT EXISTS rescheduled_to TIMESTAMPTZ;
+
+-- NOTE: Reschedules used to be recorded as a change to the same status
+UPDATE reservation_histories SET action = 'RESCHEDULE'
+WHERE from_status = to_status;
@@ -0,0 +1,10 @@
+-- Removed branch resources are only marked deleted, so reservations keep the
+-- chairs, beds, ... they held
+
+ALTER TABLE branch_resources ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+ALTER TABLE reservation_resources
+DROP CONSTRAINT IF EXISTS reservation_resources_branch_resource_id_fkey;
+ALTER TABLE reservation_resources
+ADD CONSTRAINT reservation_resources_branch_resource_id_fkey
+FOREIGN KEY (branch_resource_id) REFERENCES branch_resources (id) ON DELETE RESTRICT;
@@ -0,0 +1,11 @@
+-- Recovery codes only have to be unique per account, a clash with a code of
+-- another account must not fail generating them
+
+ALTER TABLE two_factor_recovery_codes
+DROP CONSTRAINT IF EXISTS two_factor_recovery_codes_code_hash_key;
+
+CREATE UNIQUE INDEX IF NOT EXISTS two_factor_recovery_codes_user_id_code_hash_idx
+ON two_factor_recovery_codes (user_id, code_hash);
+
+-- NOTE: Covered by the unique index
+DROP INDEX IF EXISTS two_factor_recovery_codes_user_id_idx;
@@ -0,0 +1,5 @@
+-- Deleted users no longer belong to a salon
+
+UPDATE users SET salon_id = NULL
+WHERE deleted_at IS NOT NULL
+AND salon_id IS NOT NULL;
@@ -5,5 +5,7 @@ use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 pub async fn database_connection() -> Result<Pool<Postgres>, sqlx::Error> {
     let url = env::var("DATABASE_URL").expect("DATABASE_URL must be set!");
     let pool = PgPoolOptions::new().connect(&url).await?;
+    // NOTE: Bring the schema up to date with the migrations shipped in this binary
+    sqlx::migrate!().run(&pool).await?;
     Ok(pool)
 }
@@ -10,16 +10,37 @@ use axum::{
 use axum_extra::extract::CookieJar;
 use sqlx::{Pool, Postgres};
 
-use crate::model::{
-    claim::Claims,
-    database::{User, UserRole},
-    response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        database::{Permission, User},
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::{session, two_factor},
 };
 
-const AUTH_USER_QUERY: &str = "SELECT *
+const AUTH_USER_QUERY: &str = "SELECT users.*
 FROM users
+INNER JOIN user_sessions ON user_sessions.user_id = users.id
+WHERE users.id = $1
+AND users.username = $2
+AND user_sessions.id = $3
+AND user_sessions.revoked_at IS NULL
+AND user_sessions.expires_at > now()
+AND users.deleted_at IS NULL
+";
+
+// NOTE: Only written once a minute to avoid an update on every request
+const TOUCH_SESSION_QUERY: &str = "UPDATE user_sessions SET
+last_used_at = now()
 WHERE id = $1
-AND username = $2
+AND last_used_at < now() - interval '1 minute'
+";
+
+const ROLE_PERMISSIONS_QUERY: &str = "
+SELECT permission FROM role_permissions
+WHERE role = $1
 ";
 
 pub async fn authenticated_layer(
@@ -32,13 +53,7 @@ pub async fn authenticated_layer(
         .headers()
         .get(header::AUTHORIZATION)
         .and_then(|auth_header| auth_header.to_str().ok())
-        .and_then(|auth_value| {
-            if auth_value.starts_with("Bearer ") {
-                Some(auth_value[7..].to_owned())
-            } else {
-                None
-            }
-        })
+        .and_then(|auth_value| auth_value.strip_prefix("Bearer ").map(str::to_owned))
         .or_else(|| {
             cookie_jar
                 .get("token")
@@ -57,51 +72,58 @@ pub async fn authenticated_layer(
     let user: User = match sqlx::query_as(AUTH_USER_QUERY)
         .bind(claims.id)
         .bind(claims.username.as_str())
+        .bind(claims.sid)
         .fetch_one(db.as_ref())
         .await
     {
         Ok(result) => result,
         Err(_) => return GeneralResponse::new_general(StatusCode::UNAUTHORIZED).into_response(),
     };
+    if let Err(err) = session::check_account(&user) {
+        return err.into_response();
+    }
+
+    if let Err(err) = sqlx::query(TOUCH_SESSION_QUERY)
+        .bind(claims.sid)
+        .execute(db.as_ref())
+        .await
+    {
+        return AppError::from(err).into_response();
+    }
+
+    let permissions = match sqlx::query_scalar(ROLE_PERMISSIONS_QUERY)
+        .bind(user.role)
+        .fetch_all(db.as_ref())
+        .await
+    {
+        Ok(permissions) => permissions,
+        Err(err) => return AppError::from(err).into_response(),
+    };
 
     claims.role = user.role;
+    claims.two_factor_enabled = user.totp_enabled_at.is_some();
+    claims.permissions = permissions;
 
     req.extensions_mut().insert(claims);
     next.run(req).await
 }
 
-pub async fn admin_layer(
+/// Let requests through only when the caller's role grants `permission`,
+/// added to routes with `middleware::from_fn_with_state(permission, permission_layer)`.
+pub async fn permission_layer(
+    State(permission): State<Permission>,
     Extension(claims): Extension<Claims>,
     req: Request,
     next: Next,
 ) -> Response {
-    if claims.role == Some(UserRole::Admin) {
-        next.run(req).await
-    } else {
-        GeneralResponse::new_general(StatusCode::UNAUTHORIZED).into_response()
+    if !claims.has_permission(permission) {
+        let message = format!("Missing permission {}!", permission);
+        return AppError::Forbidden(message).into_response();
     }
-}
-
-pub async fn salon_owner_layer(
-    Extension(claims): Extension<Claims>,
-    req: Request,
-    next: Next,
-) -> Response {
-    if claims.role == Some(UserRole::SalonOwner) {
-        next.run(req).await
-    } else {
-        GeneralResponse::new_general(StatusCode::UNAUTHORIZED).into_response()
-    }
-}
-
-pub async fn customer_layer(
-    Extension(claims): Extension<Claims>,
-    req: Request,
-    next: Next,
-) -> Response {
-    if claims.role == Some(UserRole::Customer) {
-        next.run(req).await
-    } else {
-        GeneralResponse::new_general(StatusCode::UNAUTHORIZED).into_response()
+    // NOTE: Accounts required to use 2FA can still sign in, but only to turn it on
+    if two_factor::required_for(claims.role) && !claims.two_factor_enabled {
+        let message = "Turn on two-factor sign-in to use this feature!".to_string();
+        return AppError::with_code(ErrorCode::TwoFactorRequired, message).into_response();
     }
+    next.run(req).await
 }
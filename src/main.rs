@@ -14,10 +14,20 @@ mod utils;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // NOTE: Load the JWT keys up front so bad settings fail at startup
+    utils::jwt_keys::JwtKeys::global();
+    model::client_info::init_client_ip()?;
+    utils::mailer::init_mailer()?;
+    utils::two_factor::init()?;
+    utils::password::init().await?;
     let db = database::database_connection().await?;
     let app = router::all_router(Arc::new(db));
     let address = SocketAddr::from((Ipv4Addr::UNSPECIFIED, 8080));
     let listener = TcpListener::bind(&address).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
@@ -1,5 +1,6 @@
 pub mod api_doc;
 pub mod claim;
+pub mod client_info;
 pub mod database;
 pub mod db_error;
 pub mod error;
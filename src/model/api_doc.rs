@@ -9,7 +9,7 @@ use uuid::Uuid;
 
 use crate::router::{
     admin::AdminApiDoc, customer::CustomerApiDoc, general::GeneralApiDoc, public::PublicApiDoc,
-    salon_owner::SalonOwnerApiDoc,
+    salon_owner::SalonOwnerApiDoc, stylist::StylistApiDoc,
 };
 
 pub struct SecurityAddon;
@@ -29,6 +29,7 @@ pub fn get_api_doc() -> openapi::OpenApi {
     let mut api_doc = PublicApiDoc::openapi();
     api_doc.merge(GeneralApiDoc::openapi());
     api_doc.merge(SalonOwnerApiDoc::openapi());
+    api_doc.merge(StylistApiDoc::openapi());
     api_doc.merge(AdminApiDoc::openapi());
     api_doc.merge(CustomerApiDoc::openapi());
 
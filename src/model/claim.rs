@@ -1,27 +1,41 @@
-use std::{
-    env,
-    time::{self, Duration, SystemTime},
-};
+use std::time::{self, Duration, SystemTime};
 
 use super::{
-    database::{User, UserRole},
+    database::{Permission, User, UserRole},
     error::AppError,
     response::GeneralResponse,
 };
-use axum::{async_trait, extract::FromRequestParts, http::request::Parts, RequestPartsExt};
+use crate::utils::jwt_keys::JwtKeys;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    RequestPartsExt,
+};
 use axum_extra::{
+    extract::cookie::{Cookie, Expiration},
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-use jsonwebtoken::{decode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, Header, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::types::time::OffsetDateTime;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Claims {
     pub id: i64,
     pub username: String,
     pub role: Option<UserRole>,
+    /// Session the token was issued for
+    pub sid: i64,
     pub exp: u64,
+    /// Whether the account has two-factor sign-in on, filled in by the
+    /// authenticated layer like `role`
+    #[serde(skip)]
+    pub two_factor_enabled: bool,
+    /// Permissions granted to `role`, filled in by the authenticated layer
+    #[serde(skip)]
+    pub permissions: Vec<Permission>,
 }
 
 #[async_trait]
@@ -38,43 +52,44 @@ where
                 Ok(header) => header,
                 Err(err) => {
                     let message = err.to_string();
-                    let res = GeneralResponse::new_error(message).unwrap();
+                    let res =
+                        GeneralResponse::new_error_with_status(StatusCode::UNAUTHORIZED, message)
+                            .unwrap();
                     return Err(res);
                 }
             };
-        let secret_key = env::var("JWT_KEY").expect("JWT_KEY must be set!");
-
         // Decode the user data
-        let token_data = match decode::<Claims>(
-            bearer.token(),
-            &DecodingKey::from_secret(secret_key.as_bytes()),
-            &Validation::default(),
-        ) {
-            Ok(data) => data,
+        match Claims::from_token(bearer.token()) {
+            Ok(claims) => Ok(claims),
             Err(err) => {
-                let message = err.to_string();
-                let res = GeneralResponse::new_error(message).unwrap();
-                return Err(res);
+                let message = match err {
+                    AppError::Unauthorized(message) => message,
+                    _ => "Unauthorized!".to_string(),
+                };
+                let res = GeneralResponse::new_error_with_status(StatusCode::UNAUTHORIZED, message)
+                    .unwrap();
+                Err(res)
             }
-        };
-
-        Ok(token_data.claims)
+        }
     }
 }
 
-pub const HOUR_TO_SECOND: u64 = 60 * 60;
+/// Access tokens are short-lived, clients get new ones with their refresh token
+pub const ACCESS_TOKEN_SECONDS: u64 = 15 * 60;
 
 impl Claims {
     pub fn from_token(token: &str) -> Result<Self, AppError> {
-        let secret_key = env::var("JWT_KEY").expect("JWT_KEY must be set!");
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(secret_key.as_bytes()),
-            &Validation::default(),
-        )?;
+        let header = decode_header(token).map_err(|err| AppError::Unauthorized(err.to_string()))?;
+        let decoding_key = JwtKeys::global()
+            .decoding_key(header.kid.as_deref(), header.alg)
+            .ok_or(AppError::Unauthorized(
+                "Unknown token signing key!".to_string(),
+            ))?;
+        let token_data = decode::<Claims>(token, decoding_key, &Validation::new(header.alg))
+            .map_err(|err| AppError::Unauthorized(err.to_string()))?;
         Ok(token_data.claims)
     }
-    pub fn create_token(user: &User) -> Result<String, AppError> {
+    pub fn create_token(user: &User, session_id: i64) -> Result<String, AppError> {
         // Extract data from db
         let id = match user.id {
             Some(id) => id,
@@ -87,24 +102,43 @@ impl Claims {
 
         // Create time expired
         let now = SystemTime::now();
-        let exp_after = Duration::from_secs(HOUR_TO_SECOND * 24 * 30);
+        let exp_after = Duration::from_secs(ACCESS_TOKEN_SECONDS);
         let exp = (now + exp_after)
             .duration_since(time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_secs();
 
-        let jwt_key = env::var("JWT_KEY").expect("JWT_KEY must be set!");
         let claims = Claims {
             id,
             username,
             role: None,
+            sid: session_id,
             exp,
+            two_factor_enabled: false,
+            permissions: Vec::new(),
         };
-        let token = jsonwebtoken::encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(jwt_key.as_bytes()),
-        )?;
+        let keys = JwtKeys::global();
+        let header = Header {
+            kid: keys.signing_key_id.clone(),
+            ..Header::new(keys.algorithm)
+        };
+        let token = jsonwebtoken::encode(&header, &claims, &keys.encoding_key)?;
         Ok(token)
     }
+
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+
+    /// Cookie carrying an access token, expiring with it.
+    pub fn token_cookie(token: &str) -> Cookie<'static> {
+        let expires =
+            Expiration::from(OffsetDateTime::now_utc() + Duration::from_secs(ACCESS_TOKEN_SECONDS));
+        Cookie::build(("token", token.to_string()))
+            .path("/")
+            .expires(expires)
+            .secure(true)
+            .http_only(true)
+            .build()
+    }
 }
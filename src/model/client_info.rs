@@ -0,0 +1,106 @@
+use std::{
+    convert::Infallible,
+    env,
+    net::{IpAddr, SocketAddr},
+    sync::OnceLock,
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header, request::Parts, HeaderName},
+};
+
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+static TRUSTED_PROXIES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+static CLIENT_IP_HEADER: OnceLock<Option<HeaderName>> = OnceLock::new();
+
+/// Load how the client address is found behind proxies:
+/// - `CLIENT_IP_HEADER`: header the hosting platform puts the client address
+///   in, like `Fly-Client-IP`. Only set it when every request goes through the
+///   platform proxy, which overwrites the header.
+/// - `TRUSTED_PROXY`: comma separated addresses of the reverse proxies trusted
+///   to set `X-Forwarded-For`. Without any, the header is ignored.
+pub fn init_client_ip() -> Result<()> {
+    let header = env::var("CLIENT_IP_HEADER")
+        .ok()
+        .map(|header| header.trim().to_string())
+        .filter(|header| !header.is_empty())
+        .map(|header| {
+            HeaderName::from_bytes(header.as_bytes())
+                .with_context(|| format!("invalid CLIENT_IP_HEADER {}!", header))
+        })
+        .transpose()?;
+    let proxies = env::var("TRUSTED_PROXY")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|proxy| !proxy.is_empty())
+        .map(|proxy| {
+            proxy
+                .parse()
+                .with_context(|| format!("invalid TRUSTED_PROXY address {}!", proxy))
+        })
+        .collect::<Result<Vec<IpAddr>>>()?;
+    let _ = CLIENT_IP_HEADER.set(header);
+    let _ = TRUSTED_PROXIES.set(proxies);
+    Ok(())
+}
+
+fn is_trusted_proxy(ip: &IpAddr) -> bool {
+    TRUSTED_PROXIES
+        .get()
+        .is_some_and(|proxies| proxies.contains(ip))
+}
+
+/// Device details of the caller, stored with its sessions.
+#[derive(Clone, Debug, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let platform = CLIENT_IP_HEADER
+            .get()
+            .and_then(Option::as_ref)
+            .and_then(|name| parts.headers.get(name))
+            .and_then(|value| value.to_str().ok())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+        // NOTE: Only a trusted proxy can tell who it forwards for. Each proxy appends the
+        // address it got the request from, so the client is the right-most untrusted one
+        let forwarded = peer.filter(is_trusted_proxy).and_then(|_| {
+            parts
+                .headers
+                .get_all(FORWARDED_FOR_HEADER)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .find(|ip| !is_trusted_proxy(ip))
+        });
+        let ip = platform.or(forwarded).or(peer).map(|ip| ip.to_string());
+        Ok(ClientInfo { user_agent, ip })
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
@@ -13,11 +13,23 @@ pub struct User {
     pub password: Option<String>,
     pub full_name: Option<String>,
     pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub gender: Option<UserGender>,
     pub role: Option<UserRole>,
     pub avatar: Option<String>,
     pub date_of_birth: Option<DateTime<Utc>>,
     pub salon_id: Option<i64>,
+    /// Base32 TOTP secret, set from 2FA setup on
+    pub totp_secret: Option<String>,
+    /// When two-factor sign-in was turned on, `None` while off
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    /// Last accepted TOTP time step
+    pub totp_last_step: Option<i64>,
+    /// Suspended accounts can't sign in or use their sessions
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_reason: Option<String>,
+    /// Deleted accounts are kept for the reservations referring to them
+    pub deleted_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -33,16 +45,67 @@ pub struct Salon {
     pub email: Option<String>,
     pub description: Option<String>,
     pub status: Option<GeneralStatus>,
+    /// Why the salon was deactivated
+    pub status_reason: Option<String>,
+    /// When an admin approved the salon, `None` while pending
+    pub approved_at: Option<DateTime<Utc>>,
+    pub cancellation_window_minutes: Option<i32>,
+    /// Customers need a verified email to book at the salon
+    pub require_verified_email: Option<bool>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, sqlx::Type, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
 #[serde(rename_all(serialize = "camelCase"))]
 #[sqlx(default)]
 pub struct SalonBranch {
     pub id: Option<i64>,
     pub address: Option<String>,
     pub salon_id: Option<i64>,
+    /// IANA time zone the opening hours are in, e.g. "Asia/Ho_Chi_Minh"
+    pub time_zone: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[sqlx(skip)]
+    pub opening_hours: Option<Vec<SalonBranchOpeningHour>>,
+}
+
+/// Weekly opening range of a salon branch, `day_of_week` counts from Monday = 0
+/// and times are local to the time zone of the branch.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SalonBranchOpeningHour {
+    pub id: Option<i64>,
+    pub salon_branch_id: Option<i64>,
+    pub day_of_week: Option<i16>,
+    pub open_time: Option<NaiveTime>,
+    pub close_time: Option<NaiveTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SalonBranchClosure {
+    pub id: Option<i64>,
+    pub salon_branch_id: Option<i64>,
+    pub time_from: Option<DateTime<Utc>>,
+    pub time_to: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Chairs, beds, ... of one kind at a salon branch, `quantity` of them can be
+/// used at the same time.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct BranchResource {
+    pub id: Option<i64>,
+    pub salon_branch_id: Option<i64>,
+    pub resource_type: Option<ResourceType>,
+    pub name: Option<String>,
+    pub quantity: Option<i32>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -55,7 +118,10 @@ pub struct Therapy {
     pub name: Option<String>,
     pub description: Option<String>,
     pub price: Option<i64>,
-    pub duration: Option<DateTime<Utc>>,
+    pub duration_minutes: Option<i32>,
+    pub buffer_minutes: Option<i32>,
+    /// One resource of each listed type is held during the therapy
+    pub resource_types: Option<Vec<ResourceType>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -67,20 +133,103 @@ pub struct Reservation {
     pub user_id: Option<i64>,
     pub therapy_id: Option<i64>,
     pub salon_branch_id: Option<i64>,
+    pub stylist_id: Option<i64>,
     pub time_from: Option<DateTime<Utc>>,
     pub time_to: Option<DateTime<Utc>>,
     pub comment: Option<String>,
     pub status: Option<ReservationStatus>,
+    pub cancel_reason: Option<String>,
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct StylistInvitation {
+    pub id: Option<i64>,
+    pub salon_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub salon_branch_ids: Option<Vec<i64>>,
+    pub status: Option<InvitationStatus>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SalonApplication {
+    pub id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub salon_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub description: Option<String>,
+    pub branch_addresses: Option<Vec<String>>,
+    /// Supporting notes of the applicant
+    pub notes: Option<String>,
+    pub status: Option<ApplicationStatus>,
+    pub review_comment: Option<String>,
+    pub reviewer_id: Option<i64>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    /// Salon created when the application was approved
+    pub salon_id: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct UserSession {
+    pub id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SignInThrottle {
+    pub scope: Option<SignInScope>,
+    pub key: Option<String>,
+    pub failed_count: Option<i32>,
+    pub last_failed_at: Option<DateTime<Utc>>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
 #[derive(IntoParams, Serialize, Deserialize, Debug, Clone)]
 pub struct GeneralPagingQueryInput {
     pub offset: Option<i64>,
     pub limit: Option<i64>,
 }
 
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn sql(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
 #[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
 #[serde(rename_all(
     serialize = "SCREAMING_SNAKE_CASE",
@@ -103,9 +252,53 @@ pub enum UserGender {
 pub enum UserRole {
     Admin,
     SalonOwner,
+    Stylist,
     Customer,
 }
 
+/// Action a role may take, granted to roles in `role_permissions`.
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "permission", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Permission {
+    ManageUsers,
+    ViewAllReservations,
+    ManageAllReservations,
+    /// Salon details, branches and therapies of the caller's salon
+    ManageSalon,
+    ManageSalonStylists,
+    ManageSalonReservations,
+    ViewAssignedReservations,
+    BookReservations,
+    RespondStylistInvitations,
+    /// Approval and activation of every salon
+    ManageSalons,
+    ApplyForSalon,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let permission = match self {
+            Permission::ManageUsers => "MANAGE_USERS",
+            Permission::ViewAllReservations => "VIEW_ALL_RESERVATIONS",
+            Permission::ManageAllReservations => "MANAGE_ALL_RESERVATIONS",
+            Permission::ManageSalon => "MANAGE_SALON",
+            Permission::ManageSalonStylists => "MANAGE_SALON_STYLISTS",
+            Permission::ManageSalonReservations => "MANAGE_SALON_RESERVATIONS",
+            Permission::ViewAssignedReservations => "VIEW_ASSIGNED_RESERVATIONS",
+            Permission::BookReservations => "BOOK_RESERVATIONS",
+            Permission::RespondStylistInvitations => "RESPOND_STYLIST_INVITATIONS",
+            Permission::ManageSalons => "MANAGE_SALONS",
+            Permission::ApplyForSalon => "APPLY_FOR_SALON",
+        };
+        write!(f, "{}", permission)
+    }
+}
+
 #[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
 #[serde(rename_all(
     serialize = "SCREAMING_SNAKE_CASE",
@@ -118,6 +311,70 @@ pub enum GeneralStatus {
     Inactivate,
 }
 
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "salon_status_action", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SalonStatusAction {
+    Approve,
+    Deactivate,
+    Reactivate,
+}
+
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "application_status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ApplicationStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "resource_type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ResourceType {
+    Chair,
+    Bed,
+    WashStation,
+}
+
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "invitation_status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum InvitationStatus {
+    Pending,
+    Accepted,
+    Declined,
+    Revoked,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[sqlx(type_name = "sign_in_scope", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SignInScope {
+    Username,
+    Ip,
+}
+
 #[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
 #[serde(rename_all(
     serialize = "SCREAMING_SNAKE_CASE",
@@ -127,10 +384,42 @@ pub enum GeneralStatus {
 #[sqlx(type_name = "reservation_status", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum ReservationStatus {
     Waiting,
+    Confirmed,
+    InProgress,
     Done,
+    NoShow,
     Cancel,
 }
 
+impl ReservationStatus {
+    /// Whether a reservation may move from this status to `next`. `Done`,
+    /// `NoShow` and `Cancel` are final.
+    pub fn can_transition_to(self, next: ReservationStatus) -> bool {
+        use ReservationStatus::*;
+        matches!(
+            (self, next),
+            (Waiting, Confirmed | InProgress | NoShow | Cancel)
+                | (Confirmed, Waiting | InProgress | Done | NoShow | Cancel)
+                | (InProgress, Done)
+        )
+    }
+}
+
+#[derive(ToSchema, Serialize, Deserialize, PartialEq, Debug, Clone, Copy, sqlx::Type)]
+#[serde(rename_all(
+    serialize = "SCREAMING_SNAKE_CASE",
+    deserialize = "SCREAMING_SNAKE_CASE"
+))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "reservation_history_action",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum ReservationHistoryAction {
+    StatusChange,
+    Reschedule,
+}
+
 impl fmt::Display for UserGender {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
@@ -142,6 +431,7 @@ impl fmt::Display for UserRole {
         let role = match self {
             UserRole::Admin => "ADMIN",
             UserRole::SalonOwner => "SALON_OWNER",
+            UserRole::Stylist => "STYLIST",
             UserRole::Customer => "CUSTOMER",
         };
         write!(f, "{}", role)
@@ -159,12 +449,31 @@ impl fmt::Display for UserRole {
 //        write!(f, "{:?}", self)
 //    }
 //}
-//
-//impl fmt::Display for ReservationStatus {
-//    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-//        write!(f, "{:?}", self)
-//    }
-//}
+
+impl fmt::Display for ResourceType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let resource_type = match self {
+            ResourceType::Chair => "CHAIR",
+            ResourceType::Bed => "BED",
+            ResourceType::WashStation => "WASH_STATION",
+        };
+        write!(f, "{}", resource_type)
+    }
+}
+
+impl fmt::Display for ReservationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            ReservationStatus::Waiting => "WAITING",
+            ReservationStatus::Confirmed => "CONFIRMED",
+            ReservationStatus::InProgress => "IN_PROGRESS",
+            ReservationStatus::Done => "DONE",
+            ReservationStatus::NoShow => "NO_SHOW",
+            ReservationStatus::Cancel => "CANCEL",
+        };
+        write!(f, "{}", status)
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
 #[serde(rename_all(serialize = "camelCase"))]
@@ -174,10 +483,41 @@ pub struct UserOutput {
     pub username: Option<String>,
     pub full_name: Option<String>,
     pub email: Option<String>,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub gender: Option<UserGender>,
     pub role: Option<UserRole>,
     pub avatar: Option<String>,
     pub date_of_birth: Option<DateTime<Utc>>,
+    pub totp_enabled_at: Option<DateTime<Utc>>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspended_reason: Option<String>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// User as other users see them, without any account details.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct PublicUserOutput {
+    pub id: Option<i64>,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    pub avatar: Option<String>,
+    pub role: Option<UserRole>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct UserSessionOutput {
+    pub id: Option<i64>,
+    pub user_agent: Option<String>,
+    pub ip: Option<String>,
+    /// Whether this is the session of the calling token
+    pub current: Option<bool>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -193,10 +533,39 @@ pub struct SalonDetailOutput {
     pub email: Option<String>,
     pub description: Option<String>,
     pub status: Option<GeneralStatus>,
+    pub cancellation_window_minutes: Option<i32>,
+    /// Customers need a verified email to book at the salon
+    pub require_verified_email: Option<bool>,
     #[sqlx(json)]
     pub salon_branches: Vec<SalonBranch>,
     #[sqlx(json)]
     pub therapies: Vec<Therapy>,
+    #[sqlx(json)]
+    pub stylists: Vec<StylistOutput>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Salon as admins see it, with its owners and branches.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct AdminSalonOutput {
+    pub id: Option<i64>,
+    pub logo: Option<String>,
+    pub cover_photo: Option<String>,
+    pub name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<GeneralStatus>,
+    pub status_reason: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub cancellation_window_minutes: Option<i32>,
+    pub require_verified_email: Option<bool>,
+    #[sqlx(json)]
+    pub owners: Vec<UserOutput>,
+    #[sqlx(json)]
+    pub salon_branches: Vec<SalonBranch>,
     pub created_at: Option<DateTime<Utc>>,
 }
 
@@ -207,11 +576,13 @@ pub struct ReservationOutput {
     pub id: Option<i64>,
     pub user_id: Option<i64>,
     pub therapy_id: Option<i64>,
-    pub salon_bed_id: Option<i64>,
+    pub salon_branch_id: Option<i64>,
+    pub stylist_id: Option<i64>,
     pub time_from: Option<DateTime<Utc>>,
     pub time_to: Option<DateTime<Utc>>,
     pub comment: Option<String>,
     pub status: Option<ReservationStatus>,
+    pub cancel_reason: Option<String>,
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -223,4 +594,166 @@ pub struct ReservationOutput {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[sqlx(json)]
     pub salon_branch: Option<SalonBranch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub customer: Option<PublicUserOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub stylist: Option<PublicUserOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub resources: Option<Vec<ReservationResourceOutput>>,
+}
+
+/// Concrete resource unit held by a reservation.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct ReservationResourceOutput {
+    pub branch_resource_id: Option<i64>,
+    pub unit: Option<i32>,
+    pub resource_type: Option<ResourceType>,
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct ReservationHistoryOutput {
+    pub id: Option<i64>,
+    pub reservation_id: Option<i64>,
+    pub action: Option<ReservationHistoryAction>,
+    pub from_status: Option<ReservationStatus>,
+    pub to_status: Option<ReservationStatus>,
+    /// Start time a rescheduled reservation moved from
+    pub rescheduled_from: Option<DateTime<Utc>>,
+    /// Start time a rescheduled reservation moved to
+    pub rescheduled_to: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub actor: Option<PublicUserOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SalonStatusHistoryOutput {
+    pub id: Option<i64>,
+    pub salon_id: Option<i64>,
+    pub action: Option<SalonStatusAction>,
+    pub reason: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub actor: Option<UserOutput>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct SalonApplicationOutput {
+    pub id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub salon_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub description: Option<String>,
+    pub branch_addresses: Option<Vec<String>>,
+    pub notes: Option<String>,
+    pub status: Option<ApplicationStatus>,
+    pub review_comment: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub salon_id: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub user: Option<UserOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub reviewer: Option<UserOutput>,
+}
+
+/// Stylist of a salon with the branches they work at.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct StylistOutput {
+    pub id: Option<i64>,
+    pub username: Option<String>,
+    pub full_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    pub avatar: Option<String>,
+    pub salon_branch_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow, Default)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[sqlx(default)]
+pub struct StylistInvitationOutput {
+    pub id: Option<i64>,
+    pub salon_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub salon_branch_ids: Option<Vec<i64>>,
+    pub status: Option<InvitationStatus>,
+    pub responded_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub salon: Option<Salon>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[sqlx(json)]
+    pub user: Option<UserOutput>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ReservationStatus::{self, *};
+
+    const ALL: [ReservationStatus; 6] = [Waiting, Confirmed, InProgress, Done, NoShow, Cancel];
+
+    #[test]
+    fn waiting_moves_forward_but_not_straight_to_done() {
+        for next in [Confirmed, InProgress, NoShow, Cancel] {
+            assert!(Waiting.can_transition_to(next), "{:?}", next);
+        }
+        assert!(!Waiting.can_transition_to(Done));
+        assert!(!Waiting.can_transition_to(Waiting));
+    }
+
+    #[test]
+    fn confirmed_can_go_back_to_waiting() {
+        for next in [Waiting, InProgress, Done, NoShow, Cancel] {
+            assert!(Confirmed.can_transition_to(next), "{:?}", next);
+        }
+        assert!(!Confirmed.can_transition_to(Confirmed));
+    }
+
+    #[test]
+    fn in_progress_can_only_finish() {
+        for next in ALL {
+            assert_eq!(
+                InProgress.can_transition_to(next),
+                next == Done,
+                "{:?}",
+                next
+            );
+        }
+    }
+
+    #[test]
+    fn final_statuses_never_change() {
+        for status in [Done, NoShow, Cancel] {
+            for next in ALL {
+                assert!(
+                    !status.can_transition_to(next),
+                    "{:?} -> {:?}",
+                    status,
+                    next
+                );
+            }
+        }
+    }
 }
@@ -1,31 +1,250 @@
 use super::response::GeneralResponse;
-use axum::response::{IntoResponse, Response};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+const UNIQUE_VIOLATION: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+const CHECK_VIOLATION: &str = "23514";
+const INVALID_TEXT_REPRESENTATION: &str = "22P02";
+
+/// Stable code sent with every error so clients don't have to parse messages.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    ValidationFailed,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    InternalError,
+    AlreadyExists,
+    RelatedNotFound,
+    InvalidValue,
+    UsernameTaken,
+    InvalidCredentials,
+    AccountSuspended,
+    TooManyAttempts,
+    WrongPassword,
+    InvalidRefreshToken,
+    InvalidResetToken,
+    InvalidVerificationToken,
+    EmailNotVerified,
+    EmailAlreadyVerified,
+    TwoFactorRequired,
+    TwoFactorAlreadyEnabled,
+    TwoFactorNotEnabled,
+    InvalidTwoFactorCode,
+    InvalidTwoFactorChallenge,
+    InvalidTimeRange,
+    SalonUnavailable,
+    SlotUnavailable,
+    BranchClosed,
+    BranchUnequipped,
+    StylistNotInBranch,
+    InvalidStatusTransition,
+    ReservationNotChangeable,
+    LastSalonOwner,
+    ApplicantNotCustomer,
+}
+
+impl ErrorCode {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ErrorCode::Unauthorized
+            | ErrorCode::InvalidCredentials
+            | ErrorCode::InvalidRefreshToken
+            | ErrorCode::InvalidTwoFactorChallenge => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden
+            | ErrorCode::AccountSuspended
+            | ErrorCode::EmailNotVerified
+            | ErrorCode::TwoFactorRequired => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::Conflict
+            | ErrorCode::AlreadyExists
+            | ErrorCode::UsernameTaken
+            | ErrorCode::EmailAlreadyVerified
+            | ErrorCode::TwoFactorAlreadyEnabled
+            | ErrorCode::SlotUnavailable
+            | ErrorCode::LastSalonOwner
+            | ErrorCode::ApplicantNotCustomer => StatusCode::CONFLICT,
+            ErrorCode::TooManyAttempts => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    /// Generic code of an error status, `None` for other statuses.
+    pub fn from_status(status: StatusCode) -> Option<ErrorCode> {
+        let code = match status {
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            status if status.is_server_error() => ErrorCode::InternalError,
+            status if status.is_client_error() => ErrorCode::BadRequest,
+            _ => return None,
+        };
+        Some(code)
+    }
+}
+
+/// Code of a single invalid input field.
+#[derive(Serialize, ToSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FieldErrorCode {
+    Required,
+    InvalidEmail,
+    InvalidPhone,
+    Negative,
+    NotPositive,
+    TooLarge,
+    InvalidTimeZone,
+    TooShort,
+    TooLong,
+    WeakPassword,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+#[serde(rename_all(serialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct FieldError {
+    /// Input field name as sent by clients
+    pub field: String,
+    pub code: FieldErrorCode,
+    pub message: String,
+}
 
 #[derive(Debug)]
-pub struct AppError(anyhow::Error);
+pub enum AppError {
+    /// Input can't be processed as it is (400)
+    BadRequest(String),
+    /// Caller isn't signed in or the token is invalid (401)
+    Unauthorized(String),
+    /// Caller is signed in but not allowed to do this (403)
+    Forbidden(String),
+    /// Row asked for doesn't exist or isn't visible to the caller (404)
+    NotFound(String),
+    /// Request clashes with existing data (409)
+    Conflict(String),
+    /// Error with a specific code, the status follows the code
+    Coded(ErrorCode, String),
+    /// Some input fields are invalid (400)
+    Validation(Vec<FieldError>),
+    /// Anything unexpected, details are logged and never sent to clients (500)
+    Internal(anyhow::Error),
+}
 
 impl AppError {
     pub fn new(err_message: String) -> Self {
-        let err = anyhow::anyhow!(err_message);
-        AppError(err)
+        AppError::BadRequest(err_message)
+    }
+
+    /// Error mapper turning a missing row into `NotFound(message)` and keeping
+    /// other database errors as they are.
+    pub fn not_found(message: &str) -> impl FnOnce(sqlx::Error) -> AppError + '_ {
+        move |err| match err {
+            sqlx::Error::RowNotFound => AppError::NotFound(message.to_string()),
+            err => AppError::from(err),
+        }
+    }
+
+    pub fn with_code(code: ErrorCode, message: String) -> Self {
+        AppError::Coded(code, message)
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::Forbidden(_) => ErrorCode::Forbidden,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Coded(code, _) => *code,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::Internal(_) => ErrorCode::InternalError,
+        }
     }
 }
 
 // Tell axum how to convert `AppError` into a response.
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let res = GeneralResponse::new_error(self.0.to_string());
-        res.into_response()
+        let code = self.code();
+        let status = code.status();
+        let res = match self {
+            AppError::BadRequest(message)
+            | AppError::Unauthorized(message)
+            | AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::Coded(_, message) => GeneralResponse::new_error_with_code(code, message),
+            AppError::Validation(errors) => GeneralResponse::new_validation_error(errors),
+            AppError::Internal(err) => {
+                eprintln!("Internal server error: {:?}", err);
+                GeneralResponse::new_general(status)
+            }
+        };
+        match res {
+            Ok(res) => res.into_response(),
+            Err(_) => status.into_response(),
+        }
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        let code = match &err {
+            sqlx::Error::RowNotFound => return AppError::NotFound("Not found!".to_string()),
+            sqlx::Error::Database(db_err) => db_err.code().map(|code| code.to_string()),
+            _ => None,
+        };
+        match code.as_deref() {
+            Some(UNIQUE_VIOLATION) => {
+                AppError::with_code(ErrorCode::AlreadyExists, "Already existed!".to_string())
+            }
+            Some(FOREIGN_KEY_VIOLATION) => AppError::with_code(
+                ErrorCode::RelatedNotFound,
+                "Related data not found!".to_string(),
+            ),
+            Some(CHECK_VIOLATION) | Some(INVALID_TEXT_REPRESENTATION) => {
+                AppError::with_code(ErrorCode::InvalidValue, "Invalid value!".to_string())
+            }
+            _ => AppError::Internal(err.into()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        AppError::Internal(err)
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(err: serde_json::Error) -> Self {
+        AppError::Internal(err.into())
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        AppError::Internal(err.into())
+    }
+}
+
+impl From<bcrypt::BcryptError> for AppError {
+    fn from(err: bcrypt::BcryptError) -> Self {
+        AppError::Internal(err.into())
     }
 }
 
-// This enables using `?` on functions that return `Result<_, anyhow::Error>` to turn them into
-// `Result<_, AppError>`. That way you don't need to do that manually.
-impl<E> From<E> for AppError
-where
-    E: Into<anyhow::Error>,
-{
-    fn from(err: E) -> Self {
-        Self(err.into())
+impl From<axum::http::header::InvalidHeaderValue> for AppError {
+    fn from(err: axum::http::header::InvalidHeaderValue) -> Self {
+        AppError::Internal(err.into())
     }
 }
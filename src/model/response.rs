@@ -1,14 +1,15 @@
-use super::error::AppError;
+use super::error::{AppError, ErrorCode, FieldError};
 use axum::{
     http::{header, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
 };
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 
 mod response_message;
 
 #[derive(Debug, Clone)]
 pub struct GeneralResponse {
+    pub status: StatusCode,
     pub header: HeaderMap,
     pub body: String,
 }
@@ -23,7 +24,11 @@ impl GeneralResponse {
         let body_obj = GeneralBody::new(status, Some(data));
         let body = serde_json::to_string(&body_obj)?;
 
-        let res = GeneralResponse { header, body };
+        let res = GeneralResponse {
+            status,
+            header,
+            body,
+        };
         Ok(res)
     }
 
@@ -32,6 +37,7 @@ impl GeneralResponse {
         let body = serde_json::to_string(&general_body)?;
 
         let res = GeneralResponse {
+            status,
             header: HeaderMap::new(),
             body,
         };
@@ -39,10 +45,40 @@ impl GeneralResponse {
     }
 
     pub fn new_error(message: String) -> Result<Self, AppError> {
+        Self::new_error_with_status(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn new_error_with_status(status: StatusCode, message: String) -> Result<Self, AppError> {
         let message = format!("Error: {}", message);
-        let general_body = GeneralBody::<bool>::new_custom(StatusCode::BAD_REQUEST, message, None);
+        let general_body = GeneralBody::<bool>::new_custom(status, message, None);
+        Self::from_error_body(status, general_body)
+    }
+
+    pub fn new_error_with_code(code: ErrorCode, message: String) -> Result<Self, AppError> {
+        let status = code.status();
+        let message = format!("Error: {}", message);
+        let mut general_body = GeneralBody::<bool>::new_custom(status, message, None);
+        general_body.code = Some(code);
+        Self::from_error_body(status, general_body)
+    }
+
+    pub fn new_validation_error(errors: Vec<FieldError>) -> Result<Self, AppError> {
+        let code = ErrorCode::ValidationFailed;
+        let status = code.status();
+        let message = "Error: Some fields are invalid!".to_string();
+        let mut general_body = GeneralBody::<bool>::new_custom(status, message, None);
+        general_body.code = Some(code);
+        general_body.errors = Some(errors);
+        Self::from_error_body(status, general_body)
+    }
+
+    fn from_error_body(
+        status: StatusCode,
+        general_body: GeneralBody<bool>,
+    ) -> Result<Self, AppError> {
         let body = serde_json::to_string(&general_body)?;
         let res = GeneralResponse {
+            status,
             header: HeaderMap::new(),
             body,
         };
@@ -55,6 +91,7 @@ impl GeneralResponse {
         let body = serde_json::to_string(&general_body)?;
 
         let res = GeneralResponse {
+            status,
             header: HeaderMap::new(),
             body,
         };
@@ -68,16 +105,22 @@ impl IntoResponse for GeneralResponse {
             header::CONTENT_TYPE,
             HeaderValue::from_static("application/json"),
         );
-        (StatusCode::OK, self.header, self.body).into_response()
+        (self.status, self.header, self.body).into_response()
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
 #[serde(rename_all(serialize = "camelCase"))]
 pub struct GeneralBody<T> {
     data: Option<T>,
     status: u16,
     message: String,
+    /// Stable error code, only sent with errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<ErrorCode>,
+    /// Invalid input fields, only sent with validation errors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    errors: Option<Vec<FieldError>>,
 }
 
 impl<T: Serialize> GeneralBody<T> {
@@ -90,6 +133,8 @@ impl<T: Serialize> GeneralBody<T> {
             data,
             status: status.as_u16(),
             message,
+            code: ErrorCode::from_status(status),
+            errors: None,
         }
     }
 
@@ -98,6 +143,8 @@ impl<T: Serialize> GeneralBody<T> {
             data,
             status: status.as_u16(),
             message,
+            code: ErrorCode::from_status(status),
+            errors: None,
         }
     }
 }
@@ -10,6 +10,9 @@ pub fn response_message() -> &'static HashMap<StatusCode, &'static str> {
         m.insert(StatusCode::INTERNAL_SERVER_ERROR, "Internal server error!");
         m.insert(StatusCode::UNAUTHORIZED, "Unauthorized!");
         m.insert(StatusCode::BAD_REQUEST, "Bad request!");
+        m.insert(StatusCode::FORBIDDEN, "Forbidden!");
+        m.insert(StatusCode::NOT_FOUND, "Not found!");
+        m.insert(StatusCode::CONFLICT, "Conflict!");
         m
     })
 }
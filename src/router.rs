@@ -11,6 +11,7 @@ pub mod customer;
 pub mod general;
 pub mod public;
 pub mod salon_owner;
+pub mod stylist;
 
 const MB_TO_BYTE: usize = 1024 * 1024;
 
@@ -39,12 +40,14 @@ fn authorization_router(db: Arc<Pool<Postgres>>) -> Router {
     let general_router = general::general_router(db.clone());
     let admin_router = admin::admin_router(db.clone());
     let salon_owner_router = salon_owner::salon_owner_router(db.clone());
+    let stylist_router = stylist::stylist_router(db.clone());
     let customer_router = customer::customer_router(db.clone());
 
     Router::new()
         .merge(general_router)
         .nest("/admin", admin_router)
         .nest("/salon-owner", salon_owner_router)
+        .nest("/stylist", stylist_router)
         .nest("/customer", customer_router)
         .layer(authenticated_layer)
 }
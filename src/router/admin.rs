@@ -2,39 +2,126 @@ use std::sync::Arc;
 
 use axum::{
     middleware,
-    routing::{delete, get, post, put},
+    routing::{delete, get, put},
     Router,
 };
 use sqlx::{Pool, Postgres};
 use utoipa::OpenApi;
 
-use crate::{layer, model::api_doc::SecurityAddon};
+use crate::{
+    layer,
+    model::{
+        api_doc::SecurityAddon,
+        database::{self, Permission},
+    },
+};
 
+mod reservation;
+mod salon;
+mod salon_application;
 mod user;
 
 pub fn admin_router(db: Arc<Pool<Postgres>>) -> Router {
-    let layer = middleware::from_fn(layer::admin_layer);
-    Router::new()
+    let manage_users =
+        middleware::from_fn_with_state(Permission::ManageUsers, layer::permission_layer);
+    let view_reservations =
+        middleware::from_fn_with_state(Permission::ViewAllReservations, layer::permission_layer);
+    let manage_reservations =
+        middleware::from_fn_with_state(Permission::ManageAllReservations, layer::permission_layer);
+    let manage_salons =
+        middleware::from_fn_with_state(Permission::ManageSalons, layer::permission_layer);
+
+    let user_router = Router::new()
         // User
         //.route("/user/salon-user", post(user::admin::create_salon_user))
-        // .route("/user/:user_id", delete(user::admin::delete_user))
         .route("/user", get(user::list_user))
+        .route("/user/:user_id", delete(user::delete_user))
+        .route("/user/:user_id/unlock", put(user::unlock_user))
+        .route("/user/:user_id/suspend", put(user::suspend_user))
+        .route("/user/:user_id/reactivate", put(user::reactivate_user))
+        .layer(manage_users);
+    let reservation_router = Router::new()
+        .route("/reservation", get(reservation::list_reservation))
+        .route("/reservation/:id", get(reservation::reservation_detail))
+        .route(
+            "/reservation/:id/history",
+            get(reservation::list_reservation_history),
+        )
+        .layer(view_reservations)
+        .merge(
+            Router::new()
+                .route(
+                    "/reservation/:id/cancel",
+                    put(reservation::cancel_reservation),
+                )
+                .layer(manage_reservations),
+        );
+    let salon_router = Router::new()
+        .route("/salon", get(salon::list_salon))
+        .route("/salon/:id", get(salon::salon_detail))
+        .route("/salon/:id/history", get(salon::list_salon_history))
+        .route("/salon/:id/approve", put(salon::approve_salon))
+        .route("/salon/:id/deactivate", put(salon::deactivate_salon))
+        .route("/salon/:id/reactivate", put(salon::reactivate_salon))
+        // Salon application
+        .route(
+            "/salon-application",
+            get(salon_application::list_application),
+        )
         .route(
-            "/customer-to-salon-owner/:user_id",
-            put(user::customer_to_salon_owner),
+            "/salon-application/:id",
+            get(salon_application::application_detail),
         )
+        .route(
+            "/salon-application/:id/approve",
+            put(salon_application::approve_application),
+        )
+        .route(
+            "/salon-application/:id/reject",
+            put(salon_application::reject_application),
+        )
+        .layer(manage_salons);
+
+    Router::new()
+        .merge(user_router)
+        .merge(reservation_router)
+        .merge(salon_router)
         .with_state(db)
-        .layer(layer)
 }
 
 #[derive(OpenApi)]
 #[openapi(
         paths(
         user::list_user,
-        user::customer_to_salon_owner
+        user::unlock_user,
+        user::suspend_user,
+        user::reactivate_user,
+        user::delete_user,
+        reservation::list_reservation,
+        reservation::reservation_detail,
+        reservation::list_reservation_history,
+        reservation::cancel_reservation,
+        salon::list_salon,
+        salon::salon_detail,
+        salon::list_salon_history,
+        salon::approve_salon,
+        salon::deactivate_salon,
+        salon::reactivate_salon,
+        salon_application::list_application,
+        salon_application::application_detail,
+        salon_application::approve_application,
+        salon_application::reject_application
         ),
         components(
             schemas(
+            user::UserSortBy,
+            user::SuspendUserInput,
+            database::SortOrder,
+            reservation::CancelReservationInput,
+            salon::SalonStatusInput,
+            database::SalonStatusAction,
+            salon_application::ReviewApplicationInput,
+            database::ApplicationStatus
         )
         ),
         modifiers(&SecurityAddon),
@@ -0,0 +1,224 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{FromRow, Pool, Postgres, Row};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    model::{
+        claim::Claims,
+        database::{Reservation, ReservationHistoryOutput, ReservationOutput, ReservationStatus},
+        error::AppError,
+        response::GeneralResponse,
+    },
+    utils::booking::{self, held_resources_join},
+};
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListReservationQueryInput {
+    pub salon_id: Option<i64>,
+    pub salon_branch_id: Option<i64>,
+    pub customer_id: Option<i64>,
+    pub stylist_id: Option<i64>,
+    pub status: Option<ReservationStatus>,
+    /// Reservations starting at or after this time
+    pub date_from: Option<DateTime<Utc>>,
+    /// Reservations starting before this time
+    pub date_to: Option<DateTime<Utc>>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const LIST_RESERVATION_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+COALESCE(to_jsonb(stylists) - 'password', 'null') as stylist,
+held.resources,
+COUNT(*) OVER () AS total
+FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+LEFT JOIN users stylists ON stylists.id = reservations.stylist_id
+",
+    held_resources_join!(),
+    "WHERE ($1::bigint IS NULL OR salon_branches.salon_id = $1)
+AND ($2::bigint IS NULL OR reservations.salon_branch_id = $2)
+AND ($3::bigint IS NULL OR reservations.user_id = $3)
+AND ($4::bigint IS NULL OR reservations.stylist_id = $4)
+AND ($5::reservation_status IS NULL OR reservations.status = $5)
+AND ($6::timestamptz IS NULL OR reservations.time_from >= $6)
+AND ($7::timestamptz IS NULL OR reservations.time_from < $7)
+ORDER BY reservations.time_from DESC
+OFFSET $8
+LIMIT $9
+"
+);
+
+/// Get list of reservations of every salon
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/admin/reservation",
+    security(("Authorization" = [])),
+    params(ListReservationQueryInput)
+)]
+pub async fn list_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Query(input): Query<ListReservationQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    let reservations = sqlx::query(LIST_RESERVATION_QUERY)
+        .bind(input.salon_id)
+        .bind(input.salon_branch_id)
+        .bind(input.customer_id)
+        .bind(input.stylist_id)
+        .bind(input.status)
+        .bind(input.date_from)
+        .bind(input.date_to)
+        .bind(input.offset)
+        .bind(input.limit)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    let mut total: Option<i64> = None;
+    let reservations: Vec<ReservationOutput> = reservations
+        .into_iter()
+        .map(|reservation| {
+            if total.is_none() {
+                total = reservation.try_get("total").ok();
+            }
+            ReservationOutput::from_row(&reservation).unwrap_or_default()
+        })
+        .collect();
+
+    let total = total.unwrap_or(0);
+
+    let data = json!({
+        "reservations": reservations,
+        "total": total
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// -----------------------------------------------------------------------------
+
+const RESERVATION_DETAIL_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+COALESCE(to_jsonb(stylists) - 'password', 'null') as stylist,
+held.resources
+FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+LEFT JOIN users stylists ON stylists.id = reservations.stylist_id
+",
+    held_resources_join!(),
+    "WHERE reservations.id = $1
+"
+);
+
+/// Get reservation detail
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/admin/reservation/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn reservation_detail(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let reservation: ReservationOutput = sqlx::query_as(RESERVATION_DETAIL_QUERY)
+        .bind(reservation_id)
+        .fetch_one(db.as_ref())
+        .await
+        .map_err(AppError::not_found("reservation not found!"))?;
+    GeneralResponse::ok_with_data(reservation)
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_RESERVATION_HISTORY_QUERY: &str = "
+SELECT reservation_histories.*,
+to_jsonb(actors) - 'password' as actor
+FROM reservation_histories
+LEFT JOIN users actors ON actors.id = reservation_histories.actor_id
+WHERE reservation_histories.reservation_id = $1
+ORDER BY reservation_histories.created_at, reservation_histories.id
+";
+
+/// Get status history of reservation
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/admin/reservation/{id}/history",
+    security(("Authorization" = [])),
+)]
+pub async fn list_reservation_history(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let histories: Vec<ReservationHistoryOutput> = sqlx::query_as(LIST_RESERVATION_HISTORY_QUERY)
+        .bind(reservation_id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(histories)
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct CancelReservationInput {
+    pub reason: String,
+}
+
+const RESERVATION_QUERY: &str = "SELECT * FROM reservations WHERE id = $1 FOR UPDATE";
+
+/// Cancel any reservation, recorded with the admin as actor
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/admin/reservation/{id}/cancel",
+    security(("Authorization" = [])),
+)]
+pub async fn cancel_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+    Json(input): Json<CancelReservationInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let reservation: Reservation = sqlx::query_as(RESERVATION_QUERY)
+        .bind(reservation_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::not_found("reservation not found!"))?;
+    let reservation = booking::transition_reservation(
+        &mut tx,
+        &reservation,
+        ReservationStatus::Cancel,
+        claims.id,
+        Some(input.reason),
+    )
+    .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(reservation)
+}
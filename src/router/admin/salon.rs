@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, Pool, Postgres, Row};
+use utoipa::{IntoParams, ToSchema};
+
+use super::user::search_pattern;
+use crate::{
+    model::{
+        claim::Claims,
+        database::{
+            AdminSalonOutput, GeneralStatus, Salon, SalonStatusAction, SalonStatusHistoryOutput,
+        },
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::validation::Validator,
+};
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListSalonQueryInput {
+    pub status: Option<GeneralStatus>,
+    /// Only approved salons when true, only pending ones when false
+    pub approved: Option<bool>,
+    /// Part of the name, email or phone, case-insensitive
+    pub search: Option<String>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const SALON_SELECT: &str = "
+SELECT salons.*,
+(
+  SELECT COALESCE(jsonb_agg(to_jsonb(owners) - 'password' ORDER BY owners.id), '[]'::jsonb)
+  FROM users owners
+  WHERE owners.salon_id = salons.id
+  AND owners.role = 'SALON_OWNER'
+) AS owners,
+(
+  SELECT COALESCE(jsonb_agg(salon_branches ORDER BY salon_branches.id), '[]'::jsonb)
+  FROM salon_branches
+  WHERE salon_branches.salon_id = salons.id
+) AS salon_branches
+";
+
+const LIST_SALON_FILTER: &str = "
+, COUNT(*) OVER () AS total
+FROM salons
+WHERE ($1::general_status IS NULL OR salons.status = $1)
+AND ($2::bool IS NULL OR (salons.approved_at IS NOT NULL) = $2)
+AND (
+  $3::text IS NULL
+  OR salons.name ILIKE $3
+  OR salons.email ILIKE $3
+  OR salons.phone ILIKE $3
+)
+ORDER BY salons.id
+OFFSET $4
+LIMIT $5
+";
+
+/// Get list of every salon, pending ones included
+#[utoipa::path(
+    get,
+    tag = "Salon",
+    path = "/admin/salon",
+    security(("Authorization" = [])),
+    params(ListSalonQueryInput)
+)]
+pub async fn list_salon(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Query(input): Query<ListSalonQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    let search = input
+        .search
+        .filter(|search| !search.trim().is_empty())
+        .map(|search| search_pattern(&search));
+    let salons = sqlx::query(&format!("{}{}", SALON_SELECT, LIST_SALON_FILTER))
+        .bind(input.status)
+        .bind(input.approved)
+        .bind(search)
+        .bind(input.offset)
+        .bind(input.limit)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    let mut total: Option<i64> = None;
+    let salons: Vec<AdminSalonOutput> = salons
+        .into_iter()
+        .map(|salon| {
+            if total.is_none() {
+                total = salon.try_get("total").ok();
+            }
+            AdminSalonOutput::from_row(&salon).unwrap_or_default()
+        })
+        .collect();
+
+    let total = total.unwrap_or(0);
+
+    let data = json!({
+        "salons": salons,
+        "total": total
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// -----------------------------------------------------------------------------
+
+/// Get salon detail with its owners and branches
+#[utoipa::path(
+    get,
+    tag = "Salon",
+    path = "/admin/salon/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn salon_detail(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(salon_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let salon: AdminSalonOutput = sqlx::query_as(&format!(
+        "{} FROM salons WHERE salons.id = $1",
+        SALON_SELECT
+    ))
+    .bind(salon_id)
+    .fetch_one(db.as_ref())
+    .await
+    .map_err(AppError::not_found("salon not found!"))?;
+    GeneralResponse::ok_with_data(salon)
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_SALON_HISTORY_QUERY: &str = "
+SELECT salon_status_histories.*,
+to_jsonb(actors) - 'password' as actor
+FROM salon_status_histories
+LEFT JOIN users actors ON actors.id = salon_status_histories.actor_id
+WHERE salon_status_histories.salon_id = $1
+ORDER BY salon_status_histories.created_at, salon_status_histories.id
+";
+
+/// Get approval and activation history of salon
+#[utoipa::path(
+    get,
+    tag = "Salon",
+    path = "/admin/salon/{id}/history",
+    security(("Authorization" = [])),
+)]
+pub async fn list_salon_history(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(salon_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let histories: Vec<SalonStatusHistoryOutput> = sqlx::query_as(LIST_SALON_HISTORY_QUERY)
+        .bind(salon_id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(histories)
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct SalonStatusInput {
+    /// Required to deactivate, shown to the salon owners
+    pub reason: Option<String>,
+}
+
+const SALON_QUERY: &str = "SELECT * FROM salons WHERE id = $1 FOR UPDATE";
+
+const UPDATE_STATUS_QUERY: &str = "
+UPDATE salons SET
+status = $2,
+status_reason = $3,
+approved_at = CASE WHEN $4 THEN now() ELSE approved_at END
+WHERE id = $1
+RETURNING *
+";
+
+const ADD_HISTORY_QUERY: &str = "
+INSERT INTO salon_status_histories (salon_id, action, actor_id, reason)
+VALUES ($1, $2, $3, $4)
+";
+
+/// Apply `action` to a salon and record it with the admin as actor.
+async fn change_status(
+    conn: &mut PgConnection,
+    claims: &Claims,
+    salon_id: i64,
+    action: SalonStatusAction,
+    reason: Option<String>,
+) -> Result<Salon, AppError> {
+    let salon: Salon = sqlx::query_as(SALON_QUERY)
+        .bind(salon_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(AppError::not_found("salon not found!"))?;
+    let is_approved = salon.approved_at.is_some();
+    let is_active = salon.status != Some(GeneralStatus::Inactivate);
+    let (allowed, status, status_reason) = match action {
+        SalonStatusAction::Approve => (!is_approved, GeneralStatus::Activate, None),
+        SalonStatusAction::Deactivate => (is_active, GeneralStatus::Inactivate, reason.clone()),
+        SalonStatusAction::Reactivate => (!is_active, GeneralStatus::Activate, None),
+    };
+    if !allowed {
+        let message = match action {
+            SalonStatusAction::Approve => "Salon is already approved!",
+            SalonStatusAction::Deactivate => "Salon is already inactive!",
+            SalonStatusAction::Reactivate => "Salon is already active!",
+        };
+        return Err(AppError::with_code(
+            ErrorCode::InvalidStatusTransition,
+            message.to_string(),
+        ));
+    }
+
+    let salon: Salon = sqlx::query_as(UPDATE_STATUS_QUERY)
+        .bind(salon_id)
+        .bind(status)
+        .bind(status_reason)
+        .bind(action == SalonStatusAction::Approve)
+        .fetch_one(&mut *conn)
+        .await?;
+    sqlx::query(ADD_HISTORY_QUERY)
+        .bind(salon_id)
+        .bind(action)
+        .bind(claims.id)
+        .bind(reason)
+        .execute(&mut *conn)
+        .await?;
+    Ok(salon)
+}
+
+/// Approve a pending salon so it's listed and takes reservations
+#[utoipa::path(
+    put,
+    tag = "Salon",
+    path = "/admin/salon/{id}/approve",
+    security(("Authorization" = [])),
+)]
+pub async fn approve_salon(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(salon_id): Path<i64>,
+    Json(input): Json<SalonStatusInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let salon = change_status(
+        &mut tx,
+        &claims,
+        salon_id,
+        SalonStatusAction::Approve,
+        input.reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(salon)
+}
+
+/// Deactivate a salon, hiding it and stopping new reservations
+#[utoipa::path(
+    put,
+    tag = "Salon",
+    path = "/admin/salon/{id}/deactivate",
+    security(("Authorization" = [])),
+)]
+pub async fn deactivate_salon(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(salon_id): Path<i64>,
+    Json(input): Json<SalonStatusInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .required("reason", input.reason.as_deref())
+        .finish()?;
+
+    let mut tx = db.begin().await?;
+    let salon = change_status(
+        &mut tx,
+        &claims,
+        salon_id,
+        SalonStatusAction::Deactivate,
+        input.reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(salon)
+}
+
+/// Reactivate a deactivated salon
+#[utoipa::path(
+    put,
+    tag = "Salon",
+    path = "/admin/salon/{id}/reactivate",
+    security(("Authorization" = [])),
+)]
+pub async fn reactivate_salon(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(salon_id): Path<i64>,
+    Json(input): Json<SalonStatusInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let salon = change_status(
+        &mut tx,
+        &claims,
+        salon_id,
+        SalonStatusAction::Reactivate,
+        input.reason,
+    )
+    .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(salon)
+}
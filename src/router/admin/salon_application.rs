@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{FromRow, PgConnection, Pool, Postgres, Row};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    model::{
+        claim::Claims,
+        database::{
+            ApplicationStatus, Salon, SalonApplication, SalonApplicationOutput, SalonStatusAction,
+            UserRole,
+        },
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::validation::Validator,
+};
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListApplicationQueryInput {
+    pub status: Option<ApplicationStatus>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const LIST_APPLICATION_QUERY: &str = "
+SELECT salon_applications.*,
+to_jsonb(applicants) - 'password' as user,
+COALESCE(to_jsonb(reviewers) - 'password', 'null') as reviewer,
+COUNT(*) OVER () AS total
+FROM salon_applications
+INNER JOIN users applicants ON applicants.id = salon_applications.user_id
+LEFT JOIN users reviewers ON reviewers.id = salon_applications.reviewer_id
+WHERE ($1::application_status IS NULL OR salon_applications.status = $1)
+ORDER BY salon_applications.created_at, salon_applications.id
+OFFSET $2
+LIMIT $3
+";
+
+/// Get list of salon applications, oldest first
+#[utoipa::path(
+    get,
+    tag = "Salon application",
+    path = "/admin/salon-application",
+    security(("Authorization" = [])),
+    params(ListApplicationQueryInput)
+)]
+pub async fn list_application(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Query(input): Query<ListApplicationQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    let applications = sqlx::query(LIST_APPLICATION_QUERY)
+        .bind(input.status)
+        .bind(input.offset)
+        .bind(input.limit)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    let mut total: Option<i64> = None;
+    let applications: Vec<SalonApplicationOutput> = applications
+        .into_iter()
+        .map(|application| {
+            if total.is_none() {
+                total = application.try_get("total").ok();
+            }
+            SalonApplicationOutput::from_row(&application).unwrap_or_default()
+        })
+        .collect();
+
+    let total = total.unwrap_or(0);
+
+    let data = json!({
+        "applications": applications,
+        "total": total
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// -----------------------------------------------------------------------------
+
+const APPLICATION_DETAIL_QUERY: &str = "
+SELECT salon_applications.*,
+to_jsonb(applicants) - 'password' as user,
+COALESCE(to_jsonb(reviewers) - 'password', 'null') as reviewer
+FROM salon_applications
+INNER JOIN users applicants ON applicants.id = salon_applications.user_id
+LEFT JOIN users reviewers ON reviewers.id = salon_applications.reviewer_id
+WHERE salon_applications.id = $1
+";
+
+/// Get salon application detail
+#[utoipa::path(
+    get,
+    tag = "Salon application",
+    path = "/admin/salon-application/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn application_detail(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(application_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let application: SalonApplicationOutput = sqlx::query_as(APPLICATION_DETAIL_QUERY)
+        .bind(application_id)
+        .fetch_one(db.as_ref())
+        .await
+        .map_err(AppError::not_found("application not found!"))?;
+    GeneralResponse::ok_with_data(application)
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct ReviewApplicationInput {
+    /// Required to reject, shown to the applicant
+    pub comment: Option<String>,
+}
+
+const PENDING_APPLICATION_QUERY: &str = "
+SELECT * FROM salon_applications
+WHERE id = $1
+AND status = 'PENDING'
+FOR UPDATE
+";
+
+/// Lock a pending application.
+async fn pending_application(
+    conn: &mut PgConnection,
+    application_id: i64,
+) -> Result<SalonApplication, AppError> {
+    let application = sqlx::query_as(PENDING_APPLICATION_QUERY)
+        .bind(application_id)
+        .fetch_one(conn)
+        .await
+        .map_err(AppError::not_found("Pending application not found!"))?;
+    Ok(application)
+}
+
+const REVIEW_APPLICATION_QUERY: &str = "
+UPDATE salon_applications SET
+status = $2,
+review_comment = $3,
+reviewer_id = $4,
+reviewed_at = now(),
+salon_id = $5
+WHERE id = $1
+RETURNING *
+";
+
+const APPLICANT_ROLE_QUERY: &str = "
+SELECT role FROM users
+WHERE id = $1
+AND deleted_at IS NULL
+FOR UPDATE
+";
+
+const ADD_SALON_QUERY: &str = "
+INSERT INTO salons (name, phone, email, description, status, approved_at)
+VALUES ($1, $2, $3, $4, 'ACTIVATE', now())
+RETURNING *
+";
+
+const ADD_SALON_BRANCHES_QUERY: &str = "
+INSERT INTO salon_branches (salon_id, address)
+SELECT $1, address FROM unnest($2::text[]) AS address
+";
+
+const BECOME_SALON_OWNER_QUERY: &str = "
+UPDATE users SET
+role = 'SALON_OWNER',
+salon_id = $1
+WHERE id = $2
+";
+
+const ADD_SALON_HISTORY_QUERY: &str = "
+INSERT INTO salon_status_histories (salon_id, action, actor_id, reason)
+VALUES ($1, $2, $3, $4)
+";
+
+/// Approve salon application, creating the salon with its branches and making
+/// the applicant its owner. Applications of users who aren't customers anymore
+/// are rejected instead.
+#[utoipa::path(
+    put,
+    tag = "Salon application",
+    path = "/admin/salon-application/{id}/approve",
+    security(("Authorization" = [])),
+)]
+pub async fn approve_application(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(application_id): Path<i64>,
+    Json(input): Json<ReviewApplicationInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let application = pending_application(&mut tx, application_id).await?;
+    let role: Option<UserRole> = sqlx::query_scalar(APPLICANT_ROLE_QUERY)
+        .bind(application.user_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+    if role != Some(UserRole::Customer) {
+        // NOTE: Rejected so the application doesn't stay pending for good
+        sqlx::query(REVIEW_APPLICATION_QUERY)
+            .bind(application_id)
+            .bind(ApplicationStatus::Rejected)
+            .bind("Only customers can become salon owners.")
+            .bind(claims.id)
+            .bind(None::<i64>)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        let message = "Applicant isn't a customer anymore, the application was rejected!";
+        return Err(AppError::with_code(
+            ErrorCode::ApplicantNotCustomer,
+            message.to_string(),
+        ));
+    }
+
+    let salon: Salon = sqlx::query_as(ADD_SALON_QUERY)
+        .bind(application.salon_name)
+        .bind(application.phone)
+        .bind(application.email)
+        .bind(application.description)
+        .fetch_one(&mut *tx)
+        .await?;
+    sqlx::query(ADD_SALON_BRANCHES_QUERY)
+        .bind(salon.id)
+        .bind(application.branch_addresses.unwrap_or_default())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(BECOME_SALON_OWNER_QUERY)
+        .bind(salon.id)
+        .bind(application.user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(ADD_SALON_HISTORY_QUERY)
+        .bind(salon.id)
+        .bind(SalonStatusAction::Approve)
+        .bind(claims.id)
+        .bind(&input.comment)
+        .execute(&mut *tx)
+        .await?;
+    let application: SalonApplication = sqlx::query_as(REVIEW_APPLICATION_QUERY)
+        .bind(application_id)
+        .bind(ApplicationStatus::Approved)
+        .bind(input.comment)
+        .bind(claims.id)
+        .bind(salon.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(application)
+}
+
+/// Reject salon application
+#[utoipa::path(
+    put,
+    tag = "Salon application",
+    path = "/admin/salon-application/{id}/reject",
+    security(("Authorization" = [])),
+)]
+pub async fn reject_application(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(application_id): Path<i64>,
+    Json(input): Json<ReviewApplicationInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .required("comment", input.comment.as_deref())
+        .finish()?;
+
+    let mut tx = db.begin().await?;
+    pending_application(&mut tx, application_id).await?;
+    let application: SalonApplication = sqlx::query_as(REVIEW_APPLICATION_QUERY)
+        .bind(application_id)
+        .bind(ApplicationStatus::Rejected)
+        .bind(input.comment)
+        .bind(claims.id)
+        .bind(None::<i64>)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(application)
+}
@@ -1,33 +1,100 @@
 use std::sync::Arc;
 
-use axum::extract::{Path, Query, State};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
 use serde_json::json;
-use sqlx::{FromRow, Pool, Postgres, Row};
+use sqlx::{FromRow, PgConnection, Pool, Postgres, Row};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::{
     model::{
-        database::{GeneralPagingQueryInput, User, UserOutput, UserRole},
-        error::AppError,
+        claim::Claims,
+        database::{Reservation, ReservationStatus, SortOrder, User, UserOutput, UserRole},
+        error::{AppError, ErrorCode},
         response::GeneralResponse,
     },
-    utils,
+    utils::{booking, session, sign_in_guard},
 };
 
+#[derive(ToSchema, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+#[serde(rename_all(deserialize = "SCREAMING_SNAKE_CASE"))]
+#[schema(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UserSortBy {
+    #[default]
+    Id,
+    Username,
+    FullName,
+    Email,
+    CreatedAt,
+}
+
+impl UserSortBy {
+    fn column(&self) -> &'static str {
+        match self {
+            UserSortBy::Id => "users.id",
+            UserSortBy::Username => "users.username",
+            UserSortBy::FullName => "users.full_name",
+            UserSortBy::Email => "users.email",
+            UserSortBy::CreatedAt => "users.created_at",
+        }
+    }
+}
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListUserQueryInput {
+    pub role: Option<UserRole>,
+    /// Part of the username, email or full name, case-insensitive
+    pub search: Option<String>,
+    pub suspended: Option<bool>,
+    /// List deleted users instead of the others
+    pub deleted: Option<bool>,
+    pub sort_by: Option<UserSortBy>,
+    pub sort_order: Option<SortOrder>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+// NOTE: The sort column and order come from enums, never from raw input
 const LIST_USER_QUERY: &str = "
 SELECT *, COUNT(*) OVER () as total
 FROM users
-ORDER BY id
-OFFSET $1
-LIMIT $2
+WHERE ($1::user_role IS NULL OR users.role = $1)
+AND (
+  $2::text IS NULL
+  OR users.username ILIKE $2
+  OR users.email ILIKE $2
+  OR users.full_name ILIKE $2
+)
+AND ($3::bool IS NULL OR (users.suspended_at IS NOT NULL) = $3)
+AND (users.deleted_at IS NOT NULL) = $4
+ORDER BY {sort_column} {sort_order} NULLS LAST, users.id
+OFFSET $5
+LIMIT $6
 ";
 
-/// Get list of users
+/// Pattern matching `search` anywhere, with its wildcards escaped.
+pub(super) fn search_pattern(search: &str) -> String {
+    let escaped = search
+        .trim()
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Get list of users, filtered, searched and sorted
 #[utoipa::path(
     get,
     tag = "User",
     path = "/admin/user",
     params(
-        GeneralPagingQueryInput
+        ListUserQueryInput
     ),
     responses(
         (status = 200, description = "Get list of users")
@@ -35,22 +102,32 @@ LIMIT $2
 )]
 pub async fn list_user(
     State(db): State<Arc<Pool<Postgres>>>,
-    Query(GeneralPagingQueryInput { offset, limit }): Query<GeneralPagingQueryInput>,
+    Query(input): Query<ListUserQueryInput>,
 ) -> Result<GeneralResponse, AppError> {
-    let users = sqlx::query(LIST_USER_QUERY)
-        .bind(offset)
-        .bind(limit)
+    let query = LIST_USER_QUERY
+        .replace("{sort_column}", input.sort_by.unwrap_or_default().column())
+        .replace("{sort_order}", input.sort_order.unwrap_or_default().sql());
+    let search = input
+        .search
+        .filter(|search| !search.trim().is_empty())
+        .map(|search| search_pattern(&search));
+    let users = sqlx::query(&query)
+        .bind(input.role)
+        .bind(search)
+        .bind(input.suspended)
+        .bind(input.deleted.unwrap_or(false))
+        .bind(input.offset)
+        .bind(input.limit)
         .fetch_all(db.as_ref())
         .await?;
     let mut total: Option<i64> = None;
     let users: Vec<UserOutput> = users
         .into_iter()
         .map(|user| {
-            if total == None {
+            if total.is_none() {
                 total = user.try_get("total").ok();
             }
-            let user = UserOutput::from_row(&user).unwrap_or_default();
-            user
+            UserOutput::from_row(&user).unwrap_or_default()
         })
         .collect();
 
@@ -61,43 +138,232 @@ pub async fn list_user(
     GeneralResponse::ok_with_data(data)
 }
 
-// -------------------------------------------------------------------------
+// ------------------------------------------------------------------------------------
 
-const CUSTOMER_TO_SALON_OWNER_QUERY: &str = "
-with salon as (
-insert INTO salons DEFAULT VALUES returning id
-)
-UPDATE users SET
-salon_id = (SELECT id FROM salon),
-role = 'SALON_OWNER'
-WHERE users.id = $1
+const USERNAME_QUERY: &str = "SELECT username FROM users WHERE id = $1";
+
+/// Unlock sign-in of an user locked out after failed attempts
+#[utoipa::path(
+    put,
+    tag = "User",
+    path = "/admin/user/{id}/unlock",
+    security(("Authorization" = []))
+)]
+pub async fn unlock_user(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Path(user_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut conn = db.acquire().await?;
+    let username: String = sqlx::query_scalar(USERNAME_QUERY)
+        .bind(user_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(AppError::not_found("user not found!"))?;
+    sign_in_guard::clear_username(&mut conn, &username).await?;
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ------------------------------------------------------------------------------------
+
+const LIVE_USER_QUERY: &str = "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE";
+
+/// Lock a not deleted user other than the calling admin.
+async fn moderated_user(
+    conn: &mut PgConnection,
+    claims: &Claims,
+    user_id: i64,
+) -> Result<User, AppError> {
+    if user_id == claims.id {
+        return Err(AppError::new(
+            "You can't do this to your own account!".to_string(),
+        ));
+    }
+    sqlx::query_as(LIVE_USER_QUERY)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await
+        .map_err(AppError::not_found("user not found!"))
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct SuspendUserInput {
+    /// Shown to the user when they try to sign in
+    pub reason: Option<String>,
+}
+
+const SUSPEND_USER_QUERY: &str = "UPDATE users SET
+suspended_at = now(),
+suspended_reason = $1
+WHERE id = $2
 RETURNING *
 ";
 
-/// Change role of an user from CUSTOMER to SALON_OWNER
-#[utoipa::path(put, tag = "User", path = "/admin/customer-to-salon-owner/{id}")]
-pub async fn customer_to_salon_owner(
+/// Suspend an user, signing them out everywhere until reactivated
+#[utoipa::path(
+    put,
+    tag = "User",
+    path = "/admin/user/{id}/suspend",
+    security(("Authorization" = []))
+)]
+pub async fn suspend_user(
     State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
     Path(user_id): Path<i64>,
+    Json(input): Json<SuspendUserInput>,
 ) -> Result<GeneralResponse, AppError> {
-    let validate_user: User = sqlx::query_as(
-        "
-SELECT * FROM users where id = $1
-",
-    )
-    .bind(user_id)
-    .fetch_one(db.as_ref())
-    .await?;
-    if let Some(role) = validate_user.role {
-        if role != UserRole::Customer {
-            return GeneralResponse::new_error("User role must be customer!".to_string());
-        }
-    }
+    let mut tx = db.begin().await?;
+    moderated_user(&mut tx, &claims, user_id).await?;
+    let user: UserOutput = sqlx::query_as(SUSPEND_USER_QUERY)
+        .bind(input.reason.filter(|reason| !reason.trim().is_empty()))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    session::revoke_user_sessions(&mut tx, user_id, None).await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(user)
+}
+
+// ------------------------------------------------------------------------------------
 
-    let user: UserOutput = sqlx::query_as(CUSTOMER_TO_SALON_OWNER_QUERY)
+const REACTIVATE_USER_QUERY: &str = "UPDATE users SET
+suspended_at = NULL,
+suspended_reason = NULL
+WHERE id = $1
+RETURNING *
+";
+
+/// Lift the suspension of an user
+#[utoipa::path(
+    put,
+    tag = "User",
+    path = "/admin/user/{id}/reactivate",
+    security(("Authorization" = []))
+)]
+pub async fn reactivate_user(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    moderated_user(&mut tx, &claims, user_id).await?;
+    let user: UserOutput = sqlx::query_as(REACTIVATE_USER_QUERY)
         .bind(user_id)
-        .fetch_one(db.as_ref())
+        .fetch_one(&mut *tx)
         .await?;
+    tx.commit().await?;
 
     GeneralResponse::ok_with_data(user)
 }
+
+// ------------------------------------------------------------------------------------
+
+const DELETE_USER_QUERY: &str = "
+UPDATE users SET
+deleted_at = now(),
+salon_id = NULL
+WHERE id = $1
+";
+
+// NOTE: Reservations the user books as customer or serves as stylist
+const UPCOMING_RESERVATIONS_QUERY: &str = "
+SELECT * FROM reservations
+WHERE (user_id = $1 OR stylist_id = $1)
+AND status IN ('WAITING', 'CONFIRMED')
+AND time_from > now()
+FOR UPDATE
+";
+
+const LEAVE_BRANCHES_QUERY: &str = "DELETE FROM stylist_branches WHERE stylist_id = $1";
+
+const LOCK_ACTIVE_SALON_QUERY: &str = "
+SELECT id FROM salons
+WHERE id = $1
+AND status = 'ACTIVATE'
+FOR UPDATE
+";
+
+const OTHER_OWNERS_QUERY: &str = "
+SELECT COUNT(*) FROM users
+WHERE salon_id = $1
+AND role = 'SALON_OWNER'
+AND id <> $2
+AND deleted_at IS NULL
+";
+
+/// Refuse to delete the last owner of an active salon, which would leave it
+/// taking reservations nobody manages.
+async fn check_not_last_owner(conn: &mut PgConnection, user: &User) -> Result<(), AppError> {
+    let (Some(UserRole::SalonOwner), Some(salon_id)) = (user.role, user.salon_id) else {
+        return Ok(());
+    };
+    let active: Option<i64> = sqlx::query_scalar(LOCK_ACTIVE_SALON_QUERY)
+        .bind(salon_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+    if active.is_none() {
+        return Ok(());
+    }
+    let other_owners: i64 = sqlx::query_scalar(OTHER_OWNERS_QUERY)
+        .bind(salon_id)
+        .bind(user.id)
+        .fetch_one(conn)
+        .await?;
+    if other_owners == 0 {
+        let message = "User is the last owner of an active salon, deactivate it first!".to_string();
+        return Err(AppError::with_code(ErrorCode::LastSalonOwner, message));
+    }
+    Ok(())
+}
+
+/// Delete an user. The account is kept for its reservation history, but it
+/// can't sign in anymore and its upcoming reservations, booked or served, are
+/// cancelled. The last owner of an active salon can't be deleted.
+#[utoipa::path(
+    delete,
+    tag = "User",
+    path = "/admin/user/{id}",
+    security(("Authorization" = []))
+)]
+pub async fn delete_user(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user = moderated_user(&mut tx, &claims, user_id).await?;
+    check_not_last_owner(&mut tx, &user).await?;
+    sqlx::query(DELETE_USER_QUERY)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    session::revoke_user_sessions(&mut tx, user_id, None).await?;
+    sqlx::query(LEAVE_BRANCHES_QUERY)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let reservations: Vec<Reservation> = sqlx::query_as(UPCOMING_RESERVATIONS_QUERY)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+    for reservation in &reservations {
+        let reason = match reservation.user_id == Some(user_id) {
+            true => "Account deleted",
+            false => "Stylist account deleted",
+        };
+        booking::transition_reservation(
+            &mut tx,
+            reservation,
+            ReservationStatus::Cancel,
+            claims.id,
+            Some(reason.to_string()),
+        )
+        .await?;
+    }
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
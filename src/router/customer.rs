@@ -2,36 +2,103 @@ use std::sync::Arc;
 
 use axum::{
     middleware,
-    routing::{delete, get, post, put},
+    routing::{get, post, put},
     Router,
 };
-use reservation::AddReservationInput;
+use reservation::{AddReservationInput, RescheduleReservationInput};
+use salon_application::AddSalonApplicationInput;
 use sqlx::{Pool, Postgres};
 use utoipa::OpenApi;
 
-use crate::{layer, model::api_doc::SecurityAddon};
+use crate::{
+    layer,
+    model::{api_doc::SecurityAddon, database::Permission},
+};
 
 mod reservation;
+mod salon_application;
+mod stylist_invitation;
 
 pub fn customer_router(db: Arc<Pool<Postgres>>) -> Router {
-    let layer = middleware::from_fn(layer::customer_layer);
-    Router::new()
+    let book_reservations =
+        middleware::from_fn_with_state(Permission::BookReservations, layer::permission_layer);
+    let respond_invitations = middleware::from_fn_with_state(
+        Permission::RespondStylistInvitations,
+        layer::permission_layer,
+    );
+    let apply_for_salon =
+        middleware::from_fn_with_state(Permission::ApplyForSalon, layer::permission_layer);
+
+    let reservation_router = Router::new()
         // Reservation
         .route("/reservation", post(reservation::add_reservation))
         .route("/reservation", get(reservation::list_reservation))
+        .route(
+            "/reservation/:id/cancel",
+            put(reservation::cancel_reservation),
+        )
+        .route(
+            "/reservation/:id/reschedule",
+            put(reservation::reschedule_reservation),
+        )
+        .route(
+            "/reservation/:id/history",
+            get(reservation::list_reservation_history),
+        )
+        .layer(book_reservations);
+    let invitation_router = Router::new()
+        // Stylist invitation
+        .route(
+            "/stylist-invitation",
+            get(stylist_invitation::list_invitation),
+        )
+        .route(
+            "/stylist-invitation/:id/accept",
+            put(stylist_invitation::accept_invitation),
+        )
+        .route(
+            "/stylist-invitation/:id/decline",
+            put(stylist_invitation::decline_invitation),
+        )
+        .layer(respond_invitations);
+    let application_router = Router::new()
+        // Salon application
+        .route(
+            "/salon-application",
+            post(salon_application::add_application),
+        )
+        .route(
+            "/salon-application",
+            get(salon_application::list_application),
+        )
+        .layer(apply_for_salon);
+
+    Router::new()
+        .merge(reservation_router)
+        .merge(invitation_router)
+        .merge(application_router)
         .with_state(db)
-        .layer(layer)
 }
 
 #[derive(OpenApi)]
 #[openapi(
         paths(
         reservation::add_reservation,
-        reservation::list_reservation
+        reservation::list_reservation,
+        reservation::cancel_reservation,
+        reservation::reschedule_reservation,
+        reservation::list_reservation_history,
+        stylist_invitation::list_invitation,
+        stylist_invitation::accept_invitation,
+        stylist_invitation::decline_invitation,
+        salon_application::add_application,
+        salon_application::list_application
         ),
         components(
             schemas(
-            AddReservationInput
+            AddReservationInput,
+            RescheduleReservationInput,
+            AddSalonApplicationInput
         )
         ),
         modifiers(&SecurityAddon),
@@ -1,22 +1,27 @@
 use std::sync::Arc;
 
-use anyhow::anyhow;
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
     http::StatusCode,
     Extension, Json,
 };
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serde::Deserialize;
 use serde_json::json;
-use sqlx::{FromRow, Pool, Postgres, Row};
+use sqlx::{FromRow, PgConnection, Pool, Postgres, Row};
 use utoipa::ToSchema;
 
-use crate::model::{
-    claim::Claims,
-    database::{GeneralPagingQueryInput, Reservation, ReservationOutput},
-    error::AppError,
-    response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        database::{
+            GeneralPagingQueryInput, Reservation, ReservationHistoryOutput, ReservationOutput,
+            ReservationStatus, Therapy,
+        },
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::booking::{self, held_resources_join},
 };
 
 #[derive(ToSchema, Deserialize, Debug, Clone)]
@@ -25,17 +30,28 @@ use crate::model::{
 pub struct AddReservationInput {
     pub therapy_id: i64,
     pub salon_branch_id: i64,
+    /// Stylist to book, any free stylist of the branch is picked when missing
+    pub stylist_id: Option<i64>,
     pub time_from: DateTime<Utc>,
     pub comment: Option<String>,
 }
 
 const ADD_RESERVATION_QUERY: &str = "
 INSERT INTO reservations
-(user_id, therapy_id, salon_branch_id, time_from, comment)
-VALUES ($1, $2, $3, $4, $5)
+(user_id, therapy_id, salon_branch_id, stylist_id, time_from, time_to, comment)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
 RETURNING *
 ";
 
+const UNVERIFIED_EMAIL_QUERY: &str = "
+SELECT salons.require_verified_email AND users.email_verified_at IS NULL
+FROM salon_branches
+INNER JOIN salons ON salons.id = salon_branches.salon_id
+CROSS JOIN users
+WHERE salon_branches.id = $1
+AND users.id = $2
+";
+
 /// Add reservation of customer
 #[utoipa::path(
     post,
@@ -48,10 +64,11 @@ pub async fn add_reservation(
     Extension(claims): Extension<Claims>,
     Json(input): Json<AddReservationInput>,
 ) -> Result<GeneralResponse, AppError> {
-    let _validate_field = sqlx::query(
+    booking::check_start_time(input.time_from, Utc::now())?;
+    let therapy: Therapy = sqlx::query_as(
         "
-SELECT salon_branches.* FROM salon_branches
-INNER JOIN therapies ON therapies.salon_id = salon_branches.salon_id
+SELECT therapies.* FROM therapies
+INNER JOIN salon_branches ON salon_branches.salon_id = therapies.salon_id
 WHERE therapies.id = $1
 AND salon_branches.id = $2
 ",
@@ -60,37 +77,87 @@ AND salon_branches.id = $2
     .bind(input.salon_branch_id)
     .fetch_one(db.as_ref())
     .await
-    .map_err(|_| anyhow!("therapy and salon branch are not in same salon!"))?;
+    .map_err(AppError::not_found(
+        "therapy and salon branch are not in same salon!",
+    ))?;
+    let unverified: bool = sqlx::query_scalar(UNVERIFIED_EMAIL_QUERY)
+        .bind(input.salon_branch_id)
+        .bind(claims.id)
+        .fetch_one(db.as_ref())
+        .await?;
+    if unverified {
+        let message = "Salon only takes reservations from verified emails!".to_string();
+        return Err(AppError::with_code(ErrorCode::EmailNotVerified, message));
+    }
+
+    let mut tx = db.begin().await?;
+    booking::lock_branch(&mut tx, input.salon_branch_id).await?;
+    booking::check_salon_bookable(&mut *tx, input.salon_branch_id).await?;
+    let availability = booking::check_availability(
+        &mut tx,
+        input.salon_branch_id,
+        &therapy,
+        input.time_from,
+        input.stylist_id,
+        None,
+    )
+    .await?;
+    if let Some(rejection) = availability.rejection() {
+        return rejection;
+    }
+    let allocation = availability.allocation();
 
-    let _reservation: Reservation = sqlx::query_as(ADD_RESERVATION_QUERY)
+    let time_to = input.time_from + booking::therapy_duration(&therapy);
+    let reservation: Reservation = sqlx::query_as(ADD_RESERVATION_QUERY)
         .bind(claims.id)
         .bind(input.therapy_id)
         .bind(input.salon_branch_id)
+        .bind(allocation.stylist_id)
         .bind(input.time_from)
+        .bind(time_to)
         .bind(input.comment)
-        .fetch_one(db.as_ref())
+        .fetch_one(&mut *tx)
         .await?;
+    let reservation_id = reservation.id.unwrap_or_default();
+    booking::save_resources(&mut tx, reservation_id, &allocation.resources).await?;
+    booking::record_history(
+        &mut tx,
+        reservation_id,
+        None,
+        ReservationStatus::Waiting,
+        claims.id,
+        None,
+    )
+    .await?;
+    tx.commit().await?;
 
     GeneralResponse::new_general(StatusCode::OK)
 }
 
 // -----------------------------------------------------------------------------
 
-const LIST_RESERVATION_QUERY: &str = "
+const LIST_RESERVATION_QUERY: &str = concat!(
+    "
 SELECT reservations.*,
 to_jsonb(salon_branches) as salon_branch,
 to_jsonb(salons) as salon,
 to_jsonb(therapies) as therapy,
+COALESCE(to_jsonb(stylists) - 'password', 'null') as stylist,
+held.resources,
 COUNT(*) OVER () AS total
 FROM reservations
 LEFT JOIN therapies ON therapies.id = reservations.therapy_id
 LEFT JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
 LEFT JOIN salons ON salons.id = salon_branches.salon_id
-WHERE reservations.user_id = $1
+LEFT JOIN users stylists ON stylists.id = reservations.stylist_id
+",
+    held_resources_join!(),
+    "WHERE reservations.user_id = $1
 ORDER BY reservations.time_from DESC
 OFFSET $2
 LIMIT $3
-";
+"
+);
 
 /// Get list of reservation of this customer
 #[utoipa::path(
@@ -116,11 +183,10 @@ pub async fn list_reservation(
     let reservations: Vec<ReservationOutput> = salons
         .into_iter()
         .map(|reservation| {
-            if total == None {
+            if total.is_none() {
                 total = reservation.try_get("total").ok();
             }
-            let reservation = ReservationOutput::from_row(&reservation).unwrap_or_default();
-            reservation
+            ReservationOutput::from_row(&reservation).unwrap_or_default()
         })
         .collect();
 
@@ -132,3 +198,232 @@ pub async fn list_reservation(
     });
     GeneralResponse::ok_with_data(data)
 }
+
+// -----------------------------------------------------------------------------
+
+const OWNED_RESERVATION_QUERY: &str = "
+SELECT reservations.*,
+salons.cancellation_window_minutes
+FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+INNER JOIN salons ON salons.id = salon_branches.salon_id
+WHERE reservations.id = $1
+AND reservations.user_id = $2
+FOR UPDATE OF reservations
+";
+
+/// Lock a reservation of the customer and check it can still be changed,
+/// along with the cancellation window of its salon in minutes.
+async fn changeable_reservation(
+    conn: &mut PgConnection,
+    reservation_id: i64,
+    user_id: i64,
+) -> Result<(Reservation, i32), AppError> {
+    let row = sqlx::query(OWNED_RESERVATION_QUERY)
+        .bind(reservation_id)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+    let reservation = Reservation::from_row(&row)?;
+    let window: i32 = row.try_get("cancellation_window_minutes")?;
+
+    let changeable = [ReservationStatus::Waiting, ReservationStatus::Confirmed];
+    if !reservation
+        .status
+        .is_some_and(|status| changeable.contains(&status))
+    {
+        let message = "Only waiting or confirmed reservations can be changed!".to_string();
+        return Err(AppError::with_code(
+            ErrorCode::ReservationNotChangeable,
+            message,
+        ));
+    }
+    if !is_before_window(reservation.time_from.unwrap_or_default(), window) {
+        let message = format!(
+            "Reservation can only be changed at least {} minutes before it starts!",
+            window
+        );
+        return Err(AppError::with_code(
+            ErrorCode::ReservationNotChangeable,
+            message,
+        ));
+    }
+    Ok((reservation, window))
+}
+
+/// Whether a reservation starting at `time_from` is still at least `window`
+/// minutes ahead.
+fn is_before_window(time_from: DateTime<Utc>, window: i32) -> bool {
+    Utc::now() <= time_from - Duration::minutes(window.into())
+}
+
+/// Cancel reservation of customer
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/customer/reservation/{id}/cancel",
+    security(("Authorization" = [])),
+)]
+pub async fn cancel_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let (reservation, _) = changeable_reservation(&mut tx, reservation_id, claims.id).await?;
+    let reservation = booking::transition_reservation(
+        &mut tx,
+        &reservation,
+        ReservationStatus::Cancel,
+        claims.id,
+        None,
+    )
+    .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(reservation)
+}
+
+// -----------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct RescheduleReservationInput {
+    pub time_from: DateTime<Utc>,
+    /// Stylist to book, the current stylist is kept when missing and still
+    /// working at the branch
+    pub stylist_id: Option<i64>,
+}
+
+const RESCHEDULE_RESERVATION_QUERY: &str = "
+UPDATE reservations SET
+time_from = $1,
+time_to = $2,
+stylist_id = $3,
+updated_at = now()
+WHERE id = $4
+RETURNING *
+";
+
+/// Move reservation of customer to another time
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/customer/reservation/{id}/reschedule",
+    security(("Authorization" = [])),
+)]
+pub async fn reschedule_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+    Json(input): Json<RescheduleReservationInput>,
+) -> Result<GeneralResponse, AppError> {
+    booking::check_start_time(input.time_from, Utc::now())?;
+    let mut tx = db.begin().await?;
+    let (reservation, window) = changeable_reservation(&mut tx, reservation_id, claims.id).await?;
+    if !is_before_window(input.time_from, window) {
+        let message = format!(
+            "Reservation can only be moved to at least {} minutes from now!",
+            window
+        );
+        return Err(AppError::with_code(
+            ErrorCode::ReservationNotChangeable,
+            message,
+        ));
+    }
+    let salon_branch_id = reservation.salon_branch_id.ok_or(AppError::new(
+        "salon branch of reservation was deleted!".to_string(),
+    ))?;
+    let therapy: Therapy = sqlx::query_as("SELECT * FROM therapies WHERE id = $1")
+        .bind(reservation.therapy_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::not_found("therapy of reservation was deleted!"))?;
+
+    booking::lock_branch(&mut tx, salon_branch_id).await?;
+    booking::check_salon_bookable(&mut *tx, salon_branch_id).await?;
+    // NOTE: The current stylist is only kept while still working at the branch,
+    // otherwise any free stylist is picked
+    let stylist_ids = booking::branch_stylist_ids(&mut *tx, salon_branch_id).await?;
+    let stylist_id = input.stylist_id.or(reservation
+        .stylist_id
+        .filter(|stylist_id| stylist_ids.contains(stylist_id)));
+    let availability = booking::check_availability(
+        &mut tx,
+        salon_branch_id,
+        &therapy,
+        input.time_from,
+        stylist_id,
+        Some(reservation_id),
+    )
+    .await?;
+    if let Some(rejection) = availability.rejection() {
+        return rejection;
+    }
+    let allocation = availability.allocation();
+
+    let time_to = input.time_from + booking::therapy_duration(&therapy);
+    let rescheduled: Reservation = sqlx::query_as(RESCHEDULE_RESERVATION_QUERY)
+        .bind(input.time_from)
+        .bind(time_to)
+        .bind(allocation.stylist_id)
+        .bind(reservation_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    booking::save_resources(&mut tx, reservation_id, &allocation.resources).await?;
+
+    booking::record_reschedule(
+        &mut tx,
+        reservation_id,
+        reservation.status.unwrap_or(ReservationStatus::Waiting),
+        reservation.time_from,
+        input.time_from,
+        claims.id,
+    )
+    .await?;
+    // NOTE: A moved reservation has to be confirmed by the salon again
+    let reservation = if rescheduled.status == Some(ReservationStatus::Confirmed) {
+        let waiting = ReservationStatus::Waiting;
+        let reason = Some("Rescheduled by the customer".to_string());
+        booking::transition_reservation(&mut tx, &rescheduled, waiting, claims.id, reason).await?
+    } else {
+        rescheduled
+    };
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(reservation)
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_RESERVATION_HISTORY_QUERY: &str = "
+SELECT reservation_histories.*,
+to_jsonb(actors) - 'password' as actor
+FROM reservation_histories
+INNER JOIN reservations ON reservations.id = reservation_histories.reservation_id
+LEFT JOIN users actors ON actors.id = reservation_histories.actor_id
+WHERE reservations.id = $1
+AND reservations.user_id = $2
+ORDER BY reservation_histories.created_at, reservation_histories.id
+";
+
+/// Get status history of reservation of customer
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/customer/reservation/{id}/history",
+    security(("Authorization" = [])),
+)]
+pub async fn list_reservation_history(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let histories: Vec<ReservationHistoryOutput> = sqlx::query_as(LIST_RESERVATION_HISTORY_QUERY)
+        .bind(reservation_id)
+        .bind(claims.id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(histories)
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use axum::{extract::State, Extension, Json};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use utoipa::ToSchema;
+
+use crate::{
+    model::{
+        claim::Claims,
+        database::SalonApplication,
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::validation::Validator,
+};
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct AddSalonApplicationInput {
+    pub salon_name: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub description: Option<String>,
+    /// Address of every branch the salon starts with
+    pub branch_addresses: Vec<String>,
+    /// Anything supporting the application, like licenses or experience
+    pub notes: Option<String>,
+}
+
+impl AddSalonApplicationInput {
+    fn validate(&self) -> Result<(), AppError> {
+        let mut validator = Validator::new();
+        validator
+            .required("salonName", self.salon_name.as_deref())
+            .email("email", self.email.as_deref())
+            .phone("phone", self.phone.as_deref());
+        if self.branch_addresses.is_empty() {
+            validator.required("branchAddresses", None);
+        }
+        for address in &self.branch_addresses {
+            validator.required("branchAddresses", Some(address));
+        }
+        validator.finish()
+    }
+}
+
+const PENDING_APPLICATION_QUERY: &str = "
+SELECT id FROM salon_applications
+WHERE user_id = $1
+AND status = 'PENDING'
+";
+
+const ADD_APPLICATION_QUERY: &str = "
+INSERT INTO salon_applications
+(user_id, salon_name, phone, email, description, branch_addresses, notes)
+VALUES ($1, $2, $3, $4, $5, $6, $7)
+RETURNING *
+";
+
+/// Apply for a salon of the customer's own, reviewed by an admin
+#[utoipa::path(
+    post,
+    tag = "Salon application",
+    path = "/customer/salon-application",
+    security(("Authorization" = [])),
+)]
+pub async fn add_application(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<AddSalonApplicationInput>,
+) -> Result<GeneralResponse, AppError> {
+    input.validate()?;
+    let pending: Option<i64> = sqlx::query_scalar(PENDING_APPLICATION_QUERY)
+        .bind(claims.id)
+        .fetch_optional(db.as_ref())
+        .await?;
+    if pending.is_some() {
+        let message = "An application of yours is still under review!".to_string();
+        return Err(AppError::with_code(ErrorCode::AlreadyExists, message));
+    }
+
+    let branch_addresses: Vec<String> = input
+        .branch_addresses
+        .iter()
+        .map(|address| address.trim().to_string())
+        .collect();
+    let application: SalonApplication = sqlx::query_as(ADD_APPLICATION_QUERY)
+        .bind(claims.id)
+        .bind(input.salon_name.map(|name| name.trim().to_string()))
+        .bind(input.phone)
+        .bind(input.email)
+        .bind(input.description)
+        .bind(branch_addresses)
+        .bind(input.notes)
+        .fetch_one(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(application)
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_APPLICATION_QUERY: &str = "
+SELECT * FROM salon_applications
+WHERE user_id = $1
+ORDER BY created_at DESC
+";
+
+/// Get salon applications of customer with their review
+#[utoipa::path(
+    get,
+    tag = "Salon application",
+    path = "/customer/salon-application",
+    security(("Authorization" = [])),
+)]
+pub async fn list_application(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let applications: Vec<SalonApplication> = sqlx::query_as(LIST_APPLICATION_QUERY)
+        .bind(claims.id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(applications)
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    Extension,
+};
+use sqlx::{PgConnection, Pool, Postgres};
+
+use crate::model::{
+    claim::Claims,
+    database::{InvitationStatus, StylistInvitation, StylistInvitationOutput},
+    error::AppError,
+    response::GeneralResponse,
+};
+
+const LIST_INVITATION_QUERY: &str = "
+SELECT stylist_invitations.*,
+to_jsonb(salons) as salon
+FROM stylist_invitations
+LEFT JOIN salons ON salons.id = stylist_invitations.salon_id
+WHERE stylist_invitations.user_id = $1
+AND stylist_invitations.status = 'PENDING'
+ORDER BY stylist_invitations.created_at DESC
+";
+
+/// Get pending stylist invitations of customer
+#[utoipa::path(
+    get,
+    tag = "Stylist invitation",
+    path = "/customer/stylist-invitation",
+    security(("Authorization" = [])),
+)]
+pub async fn list_invitation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let invitations: Vec<StylistInvitationOutput> = sqlx::query_as(LIST_INVITATION_QUERY)
+        .bind(claims.id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(invitations)
+}
+
+// -----------------------------------------------------------------------------
+
+const PENDING_INVITATION_QUERY: &str = "
+SELECT * FROM stylist_invitations
+WHERE id = $1
+AND user_id = $2
+AND status = 'PENDING'
+FOR UPDATE
+";
+
+/// Lock a pending invitation of the customer.
+async fn pending_invitation(
+    conn: &mut PgConnection,
+    invitation_id: i64,
+    user_id: i64,
+) -> Result<StylistInvitation, AppError> {
+    let invitation = sqlx::query_as(PENDING_INVITATION_QUERY)
+        .bind(invitation_id)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await
+        .map_err(AppError::not_found("Pending invitation not found!"))?;
+    Ok(invitation)
+}
+
+const ANSWER_INVITATION_QUERY: &str = "
+UPDATE stylist_invitations SET
+status = $1,
+responded_at = now()
+WHERE id = $2
+RETURNING *
+";
+
+const BECOME_STYLIST_QUERY: &str = "
+UPDATE users SET
+role = 'STYLIST',
+salon_id = $1
+WHERE id = $2
+AND role = 'CUSTOMER'
+";
+
+const ADD_STYLIST_BRANCHES_QUERY: &str = "
+INSERT INTO stylist_branches (stylist_id, salon_branch_id)
+SELECT $1, salon_branches.id FROM salon_branches
+WHERE salon_branches.salon_id = $2
+AND salon_branches.id = ANY($3)
+ON CONFLICT DO NOTHING
+";
+
+// NOTE: A stylist works for one salon, so other invitations are declined on acceptance
+const DECLINE_OTHER_INVITATION_QUERY: &str = "
+UPDATE stylist_invitations SET
+status = 'DECLINED',
+responded_at = now()
+WHERE user_id = $1
+AND id <> $2
+AND status = 'PENDING'
+";
+
+/// Accept stylist invitation, the customer becomes stylist of the inviting salon
+#[utoipa::path(
+    put,
+    tag = "Stylist invitation",
+    path = "/customer/stylist-invitation/{id}/accept",
+    security(("Authorization" = [])),
+)]
+pub async fn accept_invitation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(invitation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let invitation = pending_invitation(&mut tx, invitation_id, claims.id).await?;
+
+    let updated = sqlx::query(BECOME_STYLIST_QUERY)
+        .bind(invitation.salon_id)
+        .bind(claims.id)
+        .execute(&mut *tx)
+        .await?;
+    if updated.rows_affected() == 0 {
+        let message = "Only customers can become stylists!".to_string();
+        return Err(AppError::Forbidden(message));
+    }
+    sqlx::query(ADD_STYLIST_BRANCHES_QUERY)
+        .bind(claims.id)
+        .bind(invitation.salon_id)
+        .bind(invitation.salon_branch_ids.unwrap_or_default())
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(DECLINE_OTHER_INVITATION_QUERY)
+        .bind(claims.id)
+        .bind(invitation_id)
+        .execute(&mut *tx)
+        .await?;
+    let invitation: StylistInvitationOutput = sqlx::query_as(ANSWER_INVITATION_QUERY)
+        .bind(InvitationStatus::Accepted)
+        .bind(invitation_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(invitation)
+}
+
+/// Decline stylist invitation
+#[utoipa::path(
+    put,
+    tag = "Stylist invitation",
+    path = "/customer/stylist-invitation/{id}/decline",
+    security(("Authorization" = [])),
+)]
+pub async fn decline_invitation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(invitation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    pending_invitation(&mut tx, invitation_id, claims.id).await?;
+    let invitation: StylistInvitationOutput = sqlx::query_as(ANSWER_INVITATION_QUERY)
+        .bind(InvitationStatus::Declined)
+        .bind(invitation_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(invitation)
+}
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use axum::{
-    routing::{get, post, put},
+    routing::{delete, get, post, put},
     Router,
 };
 use sqlx::{Pool, Postgres};
@@ -15,10 +15,21 @@ pub fn general_router(db: Arc<Pool<Postgres>>) -> Router {
     Router::new()
         .route("/account/profile", get(account::get_profile))
         .route("/account/profile", put(account::update_profile))
+        .route("/account/email/resend", post(account::resend_verification))
+        .route("/account/password", put(account::change_password))
+        .route("/account/username", put(account::change_username))
+        .route("/account/sessions", get(account::list_session))
+        .route("/account/sessions/:id", delete(account::revoke_session))
+        .route("/account/2fa", get(account::two_factor_status))
+        .route("/account/2fa", delete(account::disable_two_factor))
+        .route("/account/2fa/setup", post(account::setup_two_factor))
+        .route("/account/2fa/enable", post(account::enable_two_factor))
+        .route(
+            "/account/2fa/recovery-codes",
+            post(account::regenerate_recovery_codes),
+        )
         // .route("/account/customer-to-salon-user", put(account::customer_to_salon_user))
-        // .route("/all-user/salon/:salon_id/available-salon-bed", get(salon_bed::all_user::list_available_salon_bed))
         // .route("/all-user/reservation", post(reservation::all_user::create_reservation))
-        // .route("/all-user/reservation/:reservation_id/cancel", put(reservation::all_user::cancel_reservation))
         // .route("/all-user/reservation", get(reservation::all_user::list_reservation_history))
         .with_state(db)
 }
@@ -28,10 +39,25 @@ pub fn general_router(db: Arc<Pool<Postgres>>) -> Router {
         paths(
         account::get_profile,
         account::update_profile,
+        account::resend_verification,
+        account::change_password,
+        account::change_username,
+        account::list_session,
+        account::revoke_session,
+        account::two_factor_status,
+        account::setup_two_factor,
+        account::enable_two_factor,
+        account::disable_two_factor,
+        account::regenerate_recovery_codes,
         ),
         components(
             schemas(
             account::UpdateUserProfileInput,
+            account::ChangePasswordInput,
+            account::ChangeUsernameInput,
+            account::SetupTwoFactorInput,
+            account::EnableTwoFactorInput,
+            account::DisableTwoFactorInput,
         )
         ),
         modifiers(&SecurityAddon),
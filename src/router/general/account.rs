@@ -1,13 +1,21 @@
-use crate::model::{
-    claim::Claims,
-    database::{UserGender, UserOutput},
-    error::AppError,
-    response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        database::{User, UserGender, UserOutput, UserSessionOutput},
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::{email_verification, password, session, two_factor, validation::Validator},
+};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Extension, Json,
 };
-use axum::{extract::State, Extension, Json};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres};
+use serde_json::json;
+use sqlx::{PgConnection, Pool, Postgres};
 use std::sync::Arc;
 use utoipa::ToSchema;
 
@@ -44,10 +52,17 @@ pub struct UpdateUserProfileInput {
     pub avatar: Option<String>,
 }
 
+const CURRENT_EMAIL_QUERY: &str = "SELECT email FROM users WHERE id = $1";
+
+// NOTE: A new email has to be verified again
 const UPDATE_PROFILE_QUERY: &str = "UPDATE users set
 full_name = COALESCE($1, full_name),
 date_of_birth = COALESCE($2, date_of_birth),
 email = COALESCE($3, email),
+email_verified_at = CASE
+  WHEN $3 IS DISTINCT FROM email AND $3 IS NOT NULL THEN NULL
+  ELSE email_verified_at
+END,
 gender = COALESCE($4, gender),
 avatar = COALESCE($5, avatar)
 where id = $6
@@ -66,6 +81,16 @@ pub async fn update_profile(
     Extension(claims): Extension<Claims>,
     Json(input): Json<UpdateUserProfileInput>,
 ) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .email("email", input.email.as_deref())
+        .finish()?;
+
+    let mut conn = db.acquire().await?;
+    let current_email: Option<String> = sqlx::query_scalar(CURRENT_EMAIL_QUERY)
+        .bind(claims.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let email_changed = input.email.is_some() && input.email != current_email;
     let user: UserOutput = sqlx::query_as(UPDATE_PROFILE_QUERY)
         .bind(input.full_name)
         .bind(input.date_of_birth)
@@ -73,7 +98,471 @@ pub async fn update_profile(
         .bind(input.gender)
         .bind(input.avatar)
         .bind(claims.id)
-        .fetch_one(db.as_ref())
+        .fetch_one(&mut *conn)
+        .await?;
+    if email_changed {
+        email_verification::send_verification(
+            &mut conn,
+            claims.id,
+            &claims.username,
+            &user.email.clone().unwrap_or_default(),
+        )
         .await?;
+    }
     GeneralResponse::ok_with_data(user)
 }
+
+// ---------------------------------------------------------------
+
+const LIST_SESSION_QUERY: &str = "
+SELECT user_sessions.*,
+user_sessions.id = $2 AS current
+FROM user_sessions
+WHERE user_id = $1
+AND revoked_at IS NULL
+AND expires_at > now()
+ORDER BY last_used_at DESC
+";
+
+/// Get signed-in sessions of account, one per device
+#[utoipa::path(
+    get,
+    tag = "Account",
+    path = "/account/sessions",
+    security(("Authorization" = []))
+)]
+pub async fn list_session(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let sessions: Vec<UserSessionOutput> = sqlx::query_as(LIST_SESSION_QUERY)
+        .bind(claims.id)
+        .bind(claims.sid)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(sessions)
+}
+
+// ---------------------------------------------------------------
+
+const OWN_SESSION_QUERY: &str = "
+SELECT id FROM user_sessions
+WHERE id = $1
+AND user_id = $2
+AND revoked_at IS NULL
+";
+
+/// Sign a device out by revoking its session
+#[utoipa::path(
+    delete,
+    tag = "Account",
+    path = "/account/sessions/{id}",
+    security(("Authorization" = []))
+)]
+pub async fn revoke_session(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(session_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut conn = db.acquire().await?;
+    let _: i64 = sqlx::query_scalar(OWN_SESSION_QUERY)
+        .bind(session_id)
+        .bind(claims.id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(AppError::not_found("session not found!"))?;
+    session::revoke_session(&mut conn, session_id).await?;
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ---------------------------------------------------------------
+
+const USER_QUERY: &str = "SELECT * FROM users WHERE id = $1 FOR UPDATE";
+
+/// Lock the caller's account and make sure `password` is its current password.
+async fn verify_password(
+    conn: &mut PgConnection,
+    user_id: i64,
+    password: &str,
+) -> Result<User, AppError> {
+    let user: User = sqlx::query_as(USER_QUERY)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+    if !password::verify_password(password, &user.password.clone().unwrap_or_default()).await? {
+        let message = "Current password is wrong!".to_string();
+        return Err(AppError::with_code(ErrorCode::WrongPassword, message));
+    }
+    Ok(user)
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct ChangePasswordInput {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+const UPDATE_PASSWORD_QUERY: &str = "UPDATE users SET password = $1 WHERE id = $2";
+
+/// Change password, signing out every other session
+#[utoipa::path(
+    put,
+    tag = "Account",
+    path = "/account/password",
+    security(("Authorization" = []))
+)]
+pub async fn change_password(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<ChangePasswordInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .password("newPassword", &input.new_password, Some(&claims.username))
+        .finish()?;
+
+    let mut tx = db.begin().await?;
+    verify_password(&mut tx, claims.id, &input.current_password).await?;
+    let password_hash = password::hash_password(&input.new_password).await?;
+    sqlx::query(UPDATE_PASSWORD_QUERY)
+        .bind(password_hash)
+        .bind(claims.id)
+        .execute(&mut *tx)
+        .await?;
+    session::revoke_user_sessions(&mut tx, claims.id, Some(claims.sid)).await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ---------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct ChangeUsernameInput {
+    pub username: String,
+    pub current_password: String,
+}
+
+const USERNAME_TAKEN_QUERY: &str = "
+SELECT EXISTS (SELECT 1 FROM users WHERE username = $1 AND id <> $2)
+";
+
+const UPDATE_USERNAME_QUERY: &str = "UPDATE users SET username = $1 WHERE id = $2 RETURNING *";
+
+/// Change username, the access token is issued again since it carries the username
+#[utoipa::path(
+    put,
+    tag = "Account",
+    path = "/account/username",
+    security(("Authorization" = []))
+)]
+pub async fn change_username(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<ChangeUsernameInput>,
+) -> Result<GeneralResponse, AppError> {
+    let username = input.username.trim().to_string();
+    Validator::new()
+        .required("username", Some(&username))
+        .finish()?;
+
+    let mut tx = db.begin().await?;
+    verify_password(&mut tx, claims.id, &input.current_password).await?;
+    let taken: bool = sqlx::query_scalar(USERNAME_TAKEN_QUERY)
+        .bind(&username)
+        .bind(claims.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if taken {
+        let message = "Username already existed!".to_string();
+        return Err(AppError::with_code(ErrorCode::UsernameTaken, message));
+    }
+    let user: User = sqlx::query_as(UPDATE_USERNAME_QUERY)
+        .bind(&username)
+        .bind(claims.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let token = Claims::create_token(&user, claims.sid)?;
+    let mut header = HeaderMap::new();
+    header.append(
+        header::SET_COOKIE,
+        Claims::token_cookie(&token).to_string().parse()?,
+    );
+    let data = json!({
+        "username": user.username,
+        "token": token
+    });
+    GeneralResponse::new(StatusCode::OK, header, data)
+}
+
+// ---------------------------------------------------------------
+
+// NOTE: Accounts asking again within a minute don't get another email
+const RECENT_VERIFICATION_QUERY: &str = "
+SELECT EXISTS (
+  SELECT 1 FROM email_verification_tokens
+  WHERE user_id = $1
+  AND created_at > now() - interval '1 minute'
+)
+";
+
+/// Mail a new verification link for the email of account
+#[utoipa::path(
+    post,
+    tag = "Account",
+    path = "/account/email/resend",
+    security(("Authorization" = []))
+)]
+pub async fn resend_verification(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let mut conn = db.acquire().await?;
+    let user: User = sqlx::query_as(GET_PROFILE_QUERY)
+        .bind(claims.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let Some(email) = user.email else {
+        return Err(AppError::new("Account has no email!".to_string()));
+    };
+    if user.email_verified_at.is_some() {
+        let message = "Email is already verified!".to_string();
+        return Err(AppError::with_code(
+            ErrorCode::EmailAlreadyVerified,
+            message,
+        ));
+    }
+
+    let recently_sent: bool = sqlx::query_scalar(RECENT_VERIFICATION_QUERY)
+        .bind(claims.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    if !recently_sent {
+        email_verification::send_verification(&mut conn, claims.id, &claims.username, &email)
+            .await?;
+    }
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ---------------------------------------------------------------
+
+/// Two-factor sign-in state of account
+#[utoipa::path(
+    get,
+    tag = "Account",
+    path = "/account/2fa",
+    security(("Authorization" = []))
+)]
+pub async fn two_factor_status(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let mut conn = db.acquire().await?;
+    let user: User = sqlx::query_as(GET_PROFILE_QUERY)
+        .bind(claims.id)
+        .fetch_one(&mut *conn)
+        .await?;
+    let recovery_codes_left = two_factor::recovery_codes_left(&mut conn, claims.id).await?;
+    let data = json!({
+        "enabled": user.totp_enabled_at.is_some(),
+        "enabledAt": user.totp_enabled_at,
+        "required": two_factor::required_for(user.role),
+        "recoveryCodesLeft": recovery_codes_left
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// ---------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct SetupTwoFactorInput {
+    pub current_password: String,
+}
+
+const SET_TOTP_SECRET_QUERY: &str = "UPDATE users SET
+totp_secret = $1,
+totp_last_step = NULL
+WHERE id = $2
+";
+
+fn two_factor_already_enabled() -> AppError {
+    let message = "Two-factor sign-in is already on!".to_string();
+    AppError::with_code(ErrorCode::TwoFactorAlreadyEnabled, message)
+}
+
+fn two_factor_not_enabled() -> AppError {
+    let message = "Two-factor sign-in is off!".to_string();
+    AppError::with_code(ErrorCode::TwoFactorNotEnabled, message)
+}
+
+/// Start turning on 2FA with a new secret, shown as a provisioning URI for a
+/// QR code. It is only used once confirmed with a code.
+#[utoipa::path(
+    post,
+    tag = "Account",
+    path = "/account/2fa/setup",
+    security(("Authorization" = []))
+)]
+pub async fn setup_two_factor(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<SetupTwoFactorInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user = verify_password(&mut tx, claims.id, &input.current_password).await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(two_factor_already_enabled());
+    }
+    let secret = two_factor::new_secret();
+    sqlx::query(SET_TOTP_SECRET_QUERY)
+        .bind(&secret)
+        .bind(claims.id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    let data = json!({
+        "secret": secret,
+        "otpauthUrl": two_factor::provisioning_url(&secret, &claims.username)?
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// ---------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct EnableTwoFactorInput {
+    /// Code of the authenticator app the setup secret was added to
+    pub code: String,
+}
+
+const ENABLE_TWO_FACTOR_QUERY: &str = "UPDATE users SET totp_enabled_at = now() WHERE id = $1";
+
+/// Turn on 2FA by confirming the setup secret with a code. Returns the
+/// recovery codes, which are only shown this once.
+#[utoipa::path(
+    post,
+    tag = "Account",
+    path = "/account/2fa/enable",
+    security(("Authorization" = []))
+)]
+pub async fn enable_two_factor(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<EnableTwoFactorInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user: User = sqlx::query_as(USER_QUERY)
+        .bind(claims.id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if user.totp_enabled_at.is_some() {
+        return Err(two_factor_already_enabled());
+    }
+    if user.totp_secret.is_none() {
+        let message = "Set up two-factor sign-in first!".to_string();
+        return Err(AppError::with_code(ErrorCode::TwoFactorNotEnabled, message));
+    }
+    two_factor::verify_second_factor(&mut tx, &user, Some(&input.code), None).await?;
+    sqlx::query(ENABLE_TWO_FACTOR_QUERY)
+        .bind(claims.id)
+        .execute(&mut *tx)
+        .await?;
+    let recovery_codes = two_factor::generate_recovery_codes(&mut tx, claims.id).await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(json!({ "recoveryCodes": recovery_codes }))
+}
+
+// ---------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct DisableTwoFactorInput {
+    pub current_password: String,
+    /// Code of the authenticator app
+    pub code: Option<String>,
+    /// One of the recovery codes, when the authenticator isn't at hand
+    pub recovery_code: Option<String>,
+}
+
+const DISABLE_TWO_FACTOR_QUERY: &str = "UPDATE users SET
+totp_secret = NULL,
+totp_enabled_at = NULL,
+totp_last_step = NULL
+WHERE id = $1
+";
+
+/// Turn off 2FA, confirmed with the password and a code. Not allowed for
+/// accounts whose role requires 2FA.
+#[utoipa::path(
+    delete,
+    tag = "Account",
+    path = "/account/2fa",
+    security(("Authorization" = []))
+)]
+pub async fn disable_two_factor(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<DisableTwoFactorInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user = verify_password(&mut tx, claims.id, &input.current_password).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(two_factor_not_enabled());
+    }
+    if two_factor::required_for(user.role) {
+        let message = "Two-factor sign-in is required for your account!".to_string();
+        return Err(AppError::with_code(ErrorCode::TwoFactorRequired, message));
+    }
+    two_factor::verify_second_factor(
+        &mut tx,
+        &user,
+        input.code.as_deref(),
+        input.recovery_code.as_deref(),
+    )
+    .await?;
+    sqlx::query(DISABLE_TWO_FACTOR_QUERY)
+        .bind(claims.id)
+        .execute(&mut *tx)
+        .await?;
+    two_factor::drop_recovery_codes(&mut tx, claims.id).await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ---------------------------------------------------------------
+
+/// Replace the recovery codes with new ones, shown only this once
+#[utoipa::path(
+    post,
+    tag = "Account",
+    path = "/account/2fa/recovery-codes",
+    security(("Authorization" = []))
+)]
+pub async fn regenerate_recovery_codes(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<SetupTwoFactorInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user = verify_password(&mut tx, claims.id, &input.current_password).await?;
+    if user.totp_enabled_at.is_none() {
+        return Err(two_factor_not_enabled());
+    }
+    let recovery_codes = two_factor::generate_recovery_codes(&mut tx, claims.id).await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(json!({ "recoveryCodes": recovery_codes }))
+}
@@ -4,28 +4,35 @@ use axum::{
     routing::{delete, get, post},
     Router,
 };
-use chrono::{DateTime, Utc};
 use sqlx::{Pool, Postgres};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::model::{
     api_doc::{self, SecurityAddon},
-    database,
+    database, error,
 };
 
 mod account;
+mod jwks;
 mod salon;
 
 pub fn public_router(db: Arc<Pool<Postgres>>) -> Router {
     let api_doc = api_doc::get_api_doc();
     Router::new()
         .merge(SwaggerUi::new("/swagger").url("/apidoc/openapi.json", api_doc))
+        .route("/.well-known/jwks.json", get(jwks::jwks))
         .route("/account/sign-in", post(account::sign_in))
+        .route("/account/sign-in/2fa", post(account::sign_in_two_factor))
         .route("/account/sign-up", post(account::sign_up))
+        .route("/account/refresh", post(account::refresh_token))
         .route("/account/sign-out", delete(account::sign_out))
+        .route("/account/password/forgot", post(account::forgot_password))
+        .route("/account/password/reset", post(account::reset_password))
+        .route("/account/email/verify", post(account::verify_email))
         .route("/public/salon", get(salon::list_salon))
         .route("/public/salon/:salon_id", get(salon::salon_detail))
+        .route("/public/salon/available-time", get(salon::available_time))
         //
         .with_state(db)
 }
@@ -33,18 +40,34 @@ pub fn public_router(db: Arc<Pool<Postgres>>) -> Router {
 #[derive(OpenApi)]
 #[openapi(
         paths(
+        jwks::jwks,
         account::sign_in,
+        account::sign_in_two_factor,
         account::sign_up,
+        account::refresh_token,
         account::sign_out,
+        account::forgot_password,
+        account::reset_password,
+        account::verify_email,
         salon::list_salon,
         salon::salon_detail,
+        salon::available_time,
         ),
         components(
             schemas(
             account::SigninInput,
+            account::TwoFactorSignInInput,
             account::SignupInput,
+            account::RefreshTokenInput,
+            account::SignOutInput,
+            account::ForgotPasswordInput,
+            account::ResetPasswordInput,
+            account::VerifyEmailInput,
             database::UserGender,
-            database::UserRole
+            database::UserRole,
+            error::ErrorCode,
+            error::FieldError,
+            error::FieldErrorCode
         )
         ),
         modifiers(&SecurityAddon),
@@ -1,24 +1,40 @@
-use std::{sync::Arc, time::Duration};
+use std::sync::Arc;
 
 use axum::{
     extract::State,
     http::{header, HeaderMap, StatusCode},
     Json,
 };
-use axum_extra::extract::cookie::{Cookie, Expiration};
-use chrono::{DateTime, Utc};
+use axum_extra::extract::{
+    cookie::{Cookie, Expiration},
+    CookieJar,
+};
+use chrono::{Duration, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlx::{types::time::OffsetDateTime, Pool, Postgres};
 use utoipa::ToSchema;
 
-use crate::model::{
-    claim::{Claims, HOUR_TO_SECOND},
-    database::{User, UserGender},
-    error::AppError,
-    response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        client_info::ClientInfo,
+        database::{User, UserGender},
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::{
+        email_verification,
+        mailer::{self, Mail},
+        password,
+        session::{self, RefreshToken},
+        sign_in_guard, token, two_factor,
+        validation::Validator,
+    },
 };
 
+const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+
 #[derive(ToSchema, Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
 #[schema(rename_all = "camelCase")]
@@ -30,6 +46,16 @@ pub struct SignupInput {
     gender: Option<UserGender>,
 }
 
+impl SignupInput {
+    fn validate(&self) -> Result<(), AppError> {
+        Validator::new()
+            .required("username", Some(&self.username))
+            .password("password", &self.password, Some(&self.username))
+            .email("email", self.email.as_deref())
+            .finish()
+    }
+}
+
 const SIGN_UP_QUERY: &str = "INSERT INTO
 users(username, password, full_name, email, gender)
 VALUES ($1, $2, $3, $4, $5) RETURNING *";
@@ -37,20 +63,22 @@ VALUES ($1, $2, $3, $4, $5) RETURNING *";
 #[utoipa::path(post, tag = "Account", path = "/account/sign-up")]
 pub async fn sign_up(
     State(db): State<Arc<Pool<Postgres>>>,
+    client: ClientInfo,
     Json(mut signup_input): Json<SignupInput>,
 ) -> Result<GeneralResponse, AppError> {
-    let validate_user: Vec<User> =
-        sqlx::query_as("SELECT * FROM users where username = $1")
-            .bind(signup_input.username.as_str())
-            .fetch_all(db.as_ref())
-            .await?;
+    signup_input.validate()?;
+
+    let validate_user: Vec<User> = sqlx::query_as("SELECT * FROM users where username = $1")
+        .bind(signup_input.username.as_str())
+        .fetch_all(db.as_ref())
+        .await?;
     if !validate_user.is_empty() {
         let message = "Username already existed!".to_string();
-        return GeneralResponse::new_error(message);
+        return Err(AppError::with_code(ErrorCode::UsernameTaken, message));
     }
 
     //Hash password
-    let password_hash = bcrypt::hash(signup_input.password, 4)?;
+    let password_hash = password::hash_password(&signup_input.password).await?;
     signup_input.password = password_hash;
 
     let user: User = sqlx::query_as(SIGN_UP_QUERY)
@@ -62,24 +90,13 @@ pub async fn sign_up(
         .fetch_one(db.as_ref())
         .await?;
 
-    let token = Claims::create_token(&user)?;
-
-    let cookie = Cookie::build(("token", &token))
-        .path("/")
-        .secure(true)
-        .http_only(true);
-    let mut header = HeaderMap::new();
-    header.append(header::SET_COOKIE, cookie.to_string().parse()?);
-    let data = json!({
-        "username": user.username,
-        "fullName": user.full_name,
-        "email": user.email,
-        "role": user.role,
-        "avatar": user.avatar,
-        "token": token
-    });
-
-    GeneralResponse::new(StatusCode::OK, header, data)
+    let mut conn = db.acquire().await?;
+    let user_id = user.id.unwrap_or_default();
+    if let (Some(username), Some(email)) = (&user.username, &user.email) {
+        email_verification::send_verification(&mut conn, user_id, username, email).await?;
+    }
+    let refresh_token = session::create_session(&mut conn, user_id, &client).await?;
+    signed_in_response(&user, refresh_token)
 }
 
 // ------------------------------------------------------------------------------------
@@ -94,61 +111,442 @@ pub struct SigninInput {
 
 const SIGNIN_QUERY: &str = "SELECT users.*
 FROM users
-WHERE username = $1";
+WHERE username = $1
+AND deleted_at IS NULL";
+
+const UPDATE_PASSWORD_QUERY: &str = "UPDATE users SET password = $1 WHERE id = $2";
 
+/// Sign in with username and password. Failed attempts slow down and then
+/// lock further attempts for the username and the IP.
 #[utoipa::path(post, tag = "Account", path = "/account/sign-in")]
 pub async fn sign_in(
     State(db): State<Arc<Pool<Postgres>>>,
+    client: ClientInfo,
     Json(signin_input): Json<SigninInput>,
 ) -> Result<GeneralResponse, AppError> {
-    let user: User = match sqlx::query_as(SIGNIN_QUERY)
-        .bind(signin_input.username)
-        .fetch_one(db.as_ref())
-        .await
-    {
-        Ok(user) => user,
-        Err(err) => return GeneralResponse::new_error(err.to_string()),
+    let mut conn = db.acquire().await?;
+    let ip = client.ip.as_deref();
+    sign_in_guard::reserve_attempt(&mut conn, &signin_input.username, ip).await?;
+
+    let user: Option<User> = sqlx::query_as(SIGNIN_QUERY)
+        .bind(&signin_input.username)
+        .fetch_optional(&mut *conn)
+        .await?;
+    // NOTE: Unknown usernames are checked against a dummy hash and get the same
+    // error as wrong passwords, so they can't be told apart
+    let password_hash = match &user {
+        Some(user) => user.password.clone().unwrap_or_default(),
+        None => password::dummy_hash().to_string(),
+    };
+    let is_valid_password =
+        password::verify_password(&signin_input.password, &password_hash).await?;
+    let user = match user {
+        Some(user) if is_valid_password => user,
+        _ => {
+            let message = "Wrong username or password!".to_string();
+            return Err(AppError::with_code(ErrorCode::InvalidCredentials, message));
+        }
     };
-    let is_valid_password = bcrypt::verify(
-        signin_input.password,
-        &user.password.clone().unwrap_or_default(),
-    )?;
-    if !is_valid_password {
-        return GeneralResponse::new_general(StatusCode::UNAUTHORIZED);
+    sign_in_guard::release_attempt(&mut conn, &signin_input.username, ip).await?;
+    session::check_account(&user)?;
+    if password::needs_rehash(&password_hash) {
+        sqlx::query(UPDATE_PASSWORD_QUERY)
+            .bind(password::hash_password(&signin_input.password).await?)
+            .bind(user.id)
+            .execute(&mut *conn)
+            .await?;
     }
-    let token = Claims::create_token(&user)?;
 
-    let expires =
-        Expiration::from(OffsetDateTime::now_utc() + Duration::from_secs(HOUR_TO_SECOND * 24 * 30));
-    let cookie = Cookie::build(("token", &token))
-        .path("/")
-        .expires(expires)
+    let user_id = user.id.unwrap_or_default();
+    // NOTE: Failures are only cleared once the second factor passes too, so the
+    // password can't reset the throttle while codes are guessed
+    if user.totp_enabled_at.is_some() {
+        let challenge_token = two_factor::create_challenge(&mut conn, user_id).await?;
+        let data = json!({
+            "twoFactorRequired": true,
+            "challengeToken": challenge_token
+        });
+        return GeneralResponse::ok_with_data(data);
+    }
+    sign_in_guard::clear_username(&mut conn, &signin_input.username).await?;
+
+    let refresh_token = session::create_session(&mut conn, user_id, &client).await?;
+    signed_in_response(&user, refresh_token)
+}
+
+// ------------------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct TwoFactorSignInInput {
+    /// Token returned by sign-in when the account has 2FA on
+    challenge_token: String,
+    /// Code of the authenticator app
+    code: Option<String>,
+    /// One of the recovery codes, when the authenticator isn't at hand
+    recovery_code: Option<String>,
+}
+
+/// Finish a sign-in of an account with 2FA on, with a code or a recovery code.
+/// Wrong codes count as failed sign-ins.
+#[utoipa::path(post, tag = "Account", path = "/account/sign-in/2fa")]
+pub async fn sign_in_two_factor(
+    State(db): State<Arc<Pool<Postgres>>>,
+    client: ClientInfo,
+    Json(input): Json<TwoFactorSignInInput>,
+) -> Result<GeneralResponse, AppError> {
+    // NOTE: The challenge stays locked until the session is created, so one
+    // challenge can't be finished twice
+    let mut tx = db.begin().await?;
+    let user = two_factor::challenge_user(&mut tx, &input.challenge_token).await?;
+    session::check_account(&user)?;
+    let username = user.username.clone().unwrap_or_default();
+    let ip = client.ip.as_deref();
+    sign_in_guard::reserve_attempt(&mut tx, &username, ip).await?;
+
+    let verified = two_factor::verify_second_factor(
+        &mut tx,
+        &user,
+        input.code.as_deref(),
+        input.recovery_code.as_deref(),
+    )
+    .await;
+    if let Err(err) = verified {
+        // NOTE: Only wrong codes count as failed sign-ins, otherwise rolling back
+        // takes the attempt back
+        if err.code() == ErrorCode::InvalidTwoFactorCode {
+            tx.commit().await?;
+        }
+        return Err(err);
+    }
+    two_factor::finish_challenge(&mut tx, &input.challenge_token).await?;
+    sign_in_guard::release_attempt(&mut tx, &username, ip).await?;
+    sign_in_guard::clear_username(&mut tx, &username).await?;
+
+    let refresh_token =
+        session::create_session(&mut tx, user.id.unwrap_or_default(), &client).await?;
+    tx.commit().await?;
+    signed_in_response(&user, refresh_token)
+}
+
+// ------------------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct RefreshTokenInput {
+    /// Falls back to the `refresh_token` cookie when not given
+    refresh_token: Option<String>,
+}
+
+const USER_QUERY: &str = "SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL";
+
+/// Swap a refresh token for a new access token and refresh token
+#[utoipa::path(post, tag = "Account", path = "/account/refresh")]
+pub async fn refresh_token(
+    State(db): State<Arc<Pool<Postgres>>>,
+    cookie_jar: CookieJar,
+    client: ClientInfo,
+    input: Option<Json<RefreshTokenInput>>,
+) -> Result<GeneralResponse, AppError> {
+    let token = input
+        .and_then(|Json(input)| input.refresh_token)
+        .or_else(|| refresh_token_cookie(&cookie_jar))
+        .ok_or(AppError::with_code(
+            ErrorCode::InvalidRefreshToken,
+            "Refresh token is required!".to_string(),
+        ))?;
+
+    let mut tx = db.begin().await?;
+    let rotated = session::rotate_refresh_token(&mut tx, &token, &client).await;
+    // NOTE: Commit even on failure so a reused token's session stays revoked
+    tx.commit().await?;
+    let refresh_token = rotated?;
+
+    let user: User = sqlx::query_as(USER_QUERY)
+        .bind(refresh_token.user_id)
+        .fetch_optional(db.as_ref())
+        .await?
+        .ok_or(AppError::with_code(
+            ErrorCode::InvalidRefreshToken,
+            "Refresh token is invalid or expired!".to_string(),
+        ))?;
+    session::check_account(&user)?;
+    signed_in_response(&user, refresh_token)
+}
+
+fn refresh_token_cookie(cookie_jar: &CookieJar) -> Option<String> {
+    cookie_jar
+        .get(REFRESH_TOKEN_COOKIE)
+        .map(|cookie| cookie.value().to_string())
+}
+
+/// Response of a new or refreshed session, with the access token and refresh
+/// token both in the body and in cookies.
+fn signed_in_response(
+    user: &User,
+    refresh_token: RefreshToken,
+) -> Result<GeneralResponse, AppError> {
+    let token = Claims::create_token(user, refresh_token.session_id)?;
+
+    let cookie = Claims::token_cookie(&token);
+    let refresh_expires = OffsetDateTime::from_unix_timestamp(refresh_token.expires_at.timestamp())
+        .unwrap_or(OffsetDateTime::now_utc());
+    let refresh_cookie = Cookie::build((REFRESH_TOKEN_COOKIE, &refresh_token.token))
+        .path("/account")
+        .expires(Expiration::from(refresh_expires))
         .secure(true)
         .http_only(true);
     let mut header = HeaderMap::new();
     header.append(header::SET_COOKIE, cookie.to_string().parse()?);
+    header.append(header::SET_COOKIE, refresh_cookie.to_string().parse()?);
     let data = json!({
         "username": user.username,
         "fullName": user.full_name,
         "email": user.email,
         "role": user.role,
         "avatar": user.avatar,
-        "token": token
+        "token": token,
+        "refreshToken": refresh_token.token,
+        "twoFactorSetupRequired":
+            two_factor::required_for(user.role) && user.totp_enabled_at.is_none()
     });
 
     GeneralResponse::new(StatusCode::OK, header, data)
 }
 
+// ------------------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct SignOutInput {
+    /// Falls back to the `refresh_token` cookie when not given
+    refresh_token: Option<String>,
+    /// Revoke every session of the account instead of only this one
+    everywhere: Option<bool>,
+}
+
 #[utoipa::path(delete, tag = "Account", path = "/account/sign-out")]
-pub async fn sign_out() -> Result<GeneralResponse, AppError> {
+pub async fn sign_out(
+    State(db): State<Arc<Pool<Postgres>>>,
+    cookie_jar: CookieJar,
+    input: Option<Json<SignOutInput>>,
+) -> Result<GeneralResponse, AppError> {
+    let (token, everywhere) = match input {
+        Some(Json(input)) => (input.refresh_token, input.everywhere.unwrap_or(false)),
+        None => (None, false),
+    };
+    let token = token.or_else(|| refresh_token_cookie(&cookie_jar));
+
+    let mut conn = db.acquire().await?;
+    let session = match token {
+        Some(token) => session::session_of_token(&mut conn, &token).await?,
+        None => None,
+    };
+    match session {
+        Some(session) if everywhere => {
+            session::revoke_user_sessions(&mut conn, session.user_id.unwrap_or_default(), None)
+                .await?
+        }
+        Some(session) => session::revoke_session(&mut conn, session.id.unwrap_or_default()).await?,
+        None if everywhere => {
+            return Err(AppError::with_code(
+                ErrorCode::InvalidRefreshToken,
+                "Refresh token is invalid or expired!".to_string(),
+            ))
+        }
+        None => {}
+    }
+
     let expires = Expiration::from(OffsetDateTime::UNIX_EPOCH);
     let cookie = Cookie::build(("token", ""))
         .path("/")
         .expires(expires)
         .secure(true)
         .http_only(true);
+    let refresh_cookie = Cookie::build((REFRESH_TOKEN_COOKIE, ""))
+        .path("/account")
+        .expires(expires)
+        .secure(true)
+        .http_only(true);
     let mut header = HeaderMap::new();
     header.append(header::SET_COOKIE, cookie.to_string().parse()?);
+    header.append(header::SET_COOKIE, refresh_cookie.to_string().parse()?);
 
     GeneralResponse::new(StatusCode::OK, header, Option::<bool>::None)
 }
+
+// ------------------------------------------------------------------------------------
+
+const RESET_TOKEN_MINUTES: i64 = 30;
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct ForgotPasswordInput {
+    email: String,
+}
+
+// NOTE: Accounts asking again within a minute don't get another email
+const USERS_OF_EMAIL_QUERY: &str = "
+SELECT users.* FROM users
+WHERE lower(users.email) = lower($1)
+AND users.deleted_at IS NULL
+AND NOT EXISTS (
+  SELECT 1 FROM password_reset_tokens
+  WHERE password_reset_tokens.user_id = users.id
+  AND password_reset_tokens.created_at > now() - interval '1 minute'
+)
+";
+
+const ADD_RESET_TOKEN_QUERY: &str = "
+INSERT INTO password_reset_tokens (user_id, token_hash, expires_at)
+VALUES ($1, $2, $3)
+";
+
+/// Email a password reset token to accounts of the email. Always succeeds so
+/// it can't be used to find out which emails have accounts.
+#[utoipa::path(post, tag = "Account", path = "/account/password/forgot")]
+pub async fn forgot_password(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Json(input): Json<ForgotPasswordInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .required("email", Some(&input.email))
+        .email("email", Some(&input.email))
+        .finish()?;
+
+    let users: Vec<User> = sqlx::query_as(USERS_OF_EMAIL_QUERY)
+        .bind(&input.email)
+        .fetch_all(db.as_ref())
+        .await?;
+    let mut mails = Vec::new();
+    for user in users {
+        let reset_token = token::generate_token();
+        sqlx::query(ADD_RESET_TOKEN_QUERY)
+            .bind(user.id)
+            .bind(token::hash_token(&reset_token))
+            .bind(Utc::now() + Duration::minutes(RESET_TOKEN_MINUTES))
+            .execute(db.as_ref())
+            .await?;
+        mails.push(reset_password_mail(&user, &input.email, &reset_token));
+    }
+    // NOTE: Sent in the background, so how long the response takes doesn't tell
+    // whether the email has accounts
+    tokio::spawn(async move {
+        for mail in mails {
+            mailer::send_or_log(mail).await;
+        }
+    });
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+fn reset_password_mail(user: &User, email: &str, reset_token: &str) -> Mail {
+    let link = match mailer::frontend_link("reset-password", reset_token) {
+        Some(link) => format!("\nOr open {}\n", link),
+        None => String::new(),
+    };
+    let body = format!(
+        "Hi {},\n\nUse this token to reset the password of your account: {}\n{}\nIt expires in {} minutes. If you didn't ask for it, ignore this email.\n",
+        user.username.clone().unwrap_or_default(),
+        reset_token,
+        link,
+        RESET_TOKEN_MINUTES
+    );
+    Mail {
+        to: email.to_string(),
+        subject: "Reset your password".to_string(),
+        body,
+    }
+}
+
+// ------------------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct ResetPasswordInput {
+    token: String,
+    password: String,
+}
+
+const USE_RESET_TOKEN_QUERY: &str = "
+UPDATE password_reset_tokens SET used_at = now()
+WHERE token_hash = $1
+AND used_at IS NULL
+AND expires_at > now()
+RETURNING user_id
+";
+
+// NOTE: Other tokens of the user are of no use once the password changed
+const DROP_RESET_TOKENS_QUERY: &str = "
+UPDATE password_reset_tokens SET used_at = now()
+WHERE user_id = $1
+AND used_at IS NULL
+";
+
+/// Set a new password with an emailed reset token, signing out every session
+#[utoipa::path(post, tag = "Account", path = "/account/password/reset")]
+pub async fn reset_password(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Json(input): Json<ResetPasswordInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .required("token", Some(&input.token))
+        .password("password", &input.password, None)
+        .finish()?;
+
+    let mut tx = db.begin().await?;
+    let user_id: i64 = sqlx::query_scalar(USE_RESET_TOKEN_QUERY)
+        .bind(token::hash_token(&input.token))
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::with_code(
+            ErrorCode::InvalidResetToken,
+            "Reset token is invalid or expired!".to_string(),
+        ))?;
+
+    let password_hash = password::hash_password(&input.password).await?;
+    sqlx::query(UPDATE_PASSWORD_QUERY)
+        .bind(password_hash)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(DROP_RESET_TOKENS_QUERY)
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+    session::revoke_user_sessions(&mut tx, user_id, None).await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// ------------------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct VerifyEmailInput {
+    token: String,
+}
+
+/// Verify email address with the token mailed to it
+#[utoipa::path(post, tag = "Account", path = "/account/email/verify")]
+pub async fn verify_email(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Json(input): Json<VerifyEmailInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let user = email_verification::verify_email(&mut tx, &input.token).await?;
+    tx.commit().await?;
+
+    let data = json!({
+        "email": user.email,
+        "emailVerifiedAt": user.email_verified_at
+    });
+    GeneralResponse::ok_with_data(data)
+}
@@ -0,0 +1,11 @@
+use axum::Json;
+use jsonwebtoken::jwk::JwkSet;
+
+use crate::utils::jwt_keys::JwtKeys;
+
+/// Public keys access tokens are signed with, for other services to verify
+/// them. Empty while tokens are signed with a shared secret.
+#[utoipa::path(get, tag = "Well-known", path = "/.well-known/jwks.json")]
+pub async fn jwks() -> Json<JwkSet> {
+    Json(JwtKeys::global().jwks.clone())
+}
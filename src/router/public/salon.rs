@@ -1,20 +1,25 @@
 use std::sync::Arc;
 
 use axum::extract::{Path, Query, State};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
 use serde_json::json;
 use sqlx::{FromRow, Pool, Postgres, Row};
+use utoipa::IntoParams;
 
 use crate::{
     model::{
-        database::{GeneralPagingQueryInput, Salon, SalonDetailOutput},
-        error::AppError,
+        database::{GeneralPagingQueryInput, Salon, SalonDetailOutput, Therapy},
+        error::{AppError, ErrorCode},
         response::GeneralResponse,
     },
-    utils,
+    utils::booking,
 };
 
 const LIST_SALON_QUERY: &str = "SELECT *, COUNT(*) OVER () as total
 FROM salons
+WHERE approved_at IS NOT NULL
+AND status = 'ACTIVATE'
 ORDER BY id
 OFFSET $1
 LIMIT $2";
@@ -40,11 +45,10 @@ pub async fn list_salon(
     let salons: Vec<Salon> = salons
         .into_iter()
         .map(|salon| {
-            if total == None {
+            if total.is_none() {
                 total = salon.try_get("total").ok();
             }
-            let salon = Salon::from_row(&salon).unwrap_or_default();
-            salon
+            Salon::from_row(&salon).unwrap_or_default()
         })
         .collect();
 
@@ -62,17 +66,42 @@ pub async fn list_salon(
 const SALON_DETAIL_QUERY: &str = "
 select sl.*,
 COALESCE(
-  json_agg(DISTINCT br.*) FILTER (WHERE br.id IS NOT NULL),
-  '[]'::json
+  jsonb_agg(DISTINCT to_jsonb(br) || jsonb_build_object('opening_hours', oh.opening_hours))
+  FILTER (WHERE br.id IS NOT NULL),
+  '[]'::jsonb
 ) AS salon_branches,
 COALESCE(
   json_agg(DISTINCT tp.*) FILTER (WHERE tp.id IS NOT NULL),
   '[]'::json
-) AS therapies
+) AS therapies,
+(
+  SELECT COALESCE(json_agg(st ORDER BY st.id), '[]'::json) FROM (
+    SELECT stylists.id, stylists.username, stylists.full_name, stylists.avatar,
+    COALESCE(
+      array_agg(stylist_branches.salon_branch_id) FILTER (WHERE stylist_branches.salon_branch_id IS NOT NULL),
+      '{}'
+    ) AS salon_branch_ids
+    FROM users stylists
+    LEFT JOIN stylist_branches ON stylist_branches.stylist_id = stylists.id
+    WHERE stylists.salon_id = sl.id
+    AND stylists.role = 'STYLIST'
+    AND stylists.deleted_at IS NULL
+    AND stylists.suspended_at IS NULL
+    GROUP BY stylists.id
+  ) st
+) AS stylists
 FROM salons sl
 LEFT JOIN salon_branches br ON sl.id = br.salon_id
+LEFT JOIN LATERAL (
+  SELECT COALESCE(jsonb_agg(hours ORDER BY hours.day_of_week, hours.open_time), '[]'::jsonb)
+  AS opening_hours
+  FROM salon_branch_opening_hours hours
+  WHERE hours.salon_branch_id = br.id
+) oh ON true
 LEFT JOIN therapies tp ON sl.id = tp.salon_id 
 WHERE sl.id = $1
+AND sl.approved_at IS NOT NULL
+AND sl.status = 'ACTIVATE'
 GROUP BY sl.id
 ";
 
@@ -85,6 +114,101 @@ pub async fn salon_detail(
     let salon: SalonDetailOutput = sqlx::query_as(SALON_DETAIL_QUERY)
         .bind(salon_id)
         .fetch_one(db.as_ref())
-        .await?;
+        .await
+        .map_err(AppError::not_found("salon not found!"))?;
     GeneralResponse::ok_with_data(salon)
 }
+
+// ------------------------------------------------------------
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct AvailableTimeQueryInput {
+    pub therapy_id: i64,
+    pub salon_branch_id: i64,
+    /// Only times this stylist is free, any stylist of the branch when missing
+    pub stylist_id: Option<i64>,
+    pub date_from: DateTime<Utc>,
+    pub date_to: DateTime<Utc>,
+}
+
+const THERAPY_OF_BRANCH_QUERY: &str = "
+SELECT therapies.* FROM therapies
+INNER JOIN salon_branches ON salon_branches.salon_id = therapies.salon_id
+WHERE therapies.id = $1
+AND salon_branches.id = $2
+";
+
+/// Get available start times of a therapy at a salon branch
+#[utoipa::path(
+    get,
+    tag = "Salon",
+    path = "/public/salon/available-time",
+    params(AvailableTimeQueryInput)
+)]
+pub async fn available_time(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Query(input): Query<AvailableTimeQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    if input.date_to <= input.date_from {
+        let message = "dateTo must be after dateFrom!".to_string();
+        return Err(AppError::with_code(ErrorCode::InvalidTimeRange, message));
+    }
+    if input.date_to - input.date_from > Duration::days(booking::MAX_SEARCH_DAYS) {
+        let message = format!(
+            "Date range must not be longer than {} days!",
+            booking::MAX_SEARCH_DAYS
+        );
+        return Err(AppError::with_code(ErrorCode::InvalidTimeRange, message));
+    }
+
+    let therapy: Therapy = sqlx::query_as(THERAPY_OF_BRANCH_QUERY)
+        .bind(input.therapy_id)
+        .bind(input.salon_branch_id)
+        .fetch_one(db.as_ref())
+        .await
+        .map_err(AppError::not_found(
+            "therapy and salon branch are not in same salon!",
+        ))?;
+    booking::check_salon_bookable(db.as_ref(), input.salon_branch_id).await?;
+
+    let duration = booking::therapy_duration(&therapy);
+    let buffer = booking::therapy_buffer(&therapy);
+    let search_to = input.date_to + duration + buffer;
+    let schedule = booking::BranchSchedule {
+        time_zone: booking::branch_time_zone(db.as_ref(), input.salon_branch_id).await?,
+        opening_hours: booking::opening_hours(db.as_ref(), input.salon_branch_id).await?,
+        closures: booking::closure_times(
+            db.as_ref(),
+            input.salon_branch_id,
+            input.date_from,
+            search_to,
+        )
+        .await?,
+        stylist_ids: booking::branch_stylist_ids(db.as_ref(), input.salon_branch_id).await?,
+        resources: booking::branch_resources(db.as_ref(), input.salon_branch_id).await?,
+    };
+    booking::check_stylist(&schedule, input.stylist_id)?;
+    let busy_times = booking::busy_times(
+        db.as_ref(),
+        input.salon_branch_id,
+        input.date_from,
+        search_to,
+        None,
+    )
+    .await?;
+    let available_times = booking::available_start_times(
+        input.date_from,
+        input.date_to,
+        &therapy,
+        input.stylist_id,
+        &schedule,
+        &busy_times,
+    );
+
+    let data = json!({
+        "availableTimes": available_times
+    });
+    GeneralResponse::ok_with_data(data)
+}
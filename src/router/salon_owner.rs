@@ -5,32 +5,127 @@ use axum::{
     routing::{delete, get, post, put},
     Router,
 };
+use reservation::CancelReservationInput;
 use salon::UpdateSalonInput;
-use salon_branch::AddSalonBranchInput;
+use salon_branch::{
+    AddBranchResourceInput, AddClosureInput, AddSalonBranchInput, OpeningHourInput,
+    UpdateBranchResourceInput, UpdateOpeningHoursInput,
+};
 use sqlx::{Pool, Postgres};
+use stylist::{InviteStylistInput, UpdateStylistBranchesInput};
 use therapy::AddAndUpdateTherapyInput;
 use utoipa::OpenApi;
 
-use crate::{layer, model::api_doc::SecurityAddon};
+use crate::{
+    layer,
+    model::{api_doc::SecurityAddon, database::Permission},
+};
 
+mod reservation;
 mod salon;
 mod salon_branch;
+mod stylist;
 mod therapy;
 
 pub fn salon_owner_router(db: Arc<Pool<Postgres>>) -> Router {
-    let layer = middleware::from_fn(layer::salon_owner_layer);
-    Router::new()
+    let manage_salon =
+        middleware::from_fn_with_state(Permission::ManageSalon, layer::permission_layer);
+    let manage_stylists =
+        middleware::from_fn_with_state(Permission::ManageSalonStylists, layer::permission_layer);
+    let manage_reservations = middleware::from_fn_with_state(
+        Permission::ManageSalonReservations,
+        layer::permission_layer,
+    );
+
+    let salon_router = Router::new()
         // Salon
         .route("/salon", get(salon::get_salon))
         .route("/salon", put(salon::update_salon))
         .route("/salon/branch", post(salon_branch::add_branch))
         .route("/salon/branch/:id", delete(salon_branch::delete_branch))
+        .route(
+            "/salon/branch/:id/hours",
+            get(salon_branch::list_opening_hours),
+        )
+        .route(
+            "/salon/branch/:id/hours",
+            put(salon_branch::update_opening_hours),
+        )
+        .route(
+            "/salon/branch/:id/closures",
+            get(salon_branch::list_closures),
+        )
+        .route(
+            "/salon/branch/:id/closures",
+            post(salon_branch::add_closure),
+        )
+        .route(
+            "/salon/branch/:id/closures/:closure_id",
+            delete(salon_branch::delete_closure),
+        )
+        .route(
+            "/salon/branch/:id/resources",
+            get(salon_branch::list_resources),
+        )
+        .route(
+            "/salon/branch/:id/resources",
+            post(salon_branch::add_resource),
+        )
+        .route(
+            "/salon/branch/:id/resources/:resource_id",
+            put(salon_branch::update_resource),
+        )
+        .route(
+            "/salon/branch/:id/resources/:resource_id",
+            delete(salon_branch::delete_resource),
+        )
         .route("/salon/therapy", post(therapy::add_therapy))
         .route("/salon/therapy/:therapy_id", put(therapy::update_therapy))
         .route(
             "/salon/therapy/:therapy_id",
             delete(therapy::delete_therapy),
         )
+        .layer(manage_salon);
+    let stylist_router = Router::new()
+        // Stylist
+        .route("/stylist", get(stylist::list_stylist))
+        .route("/stylist/invitation", get(stylist::list_invitation))
+        .route("/stylist/invitation", post(stylist::invite_stylist))
+        .route(
+            "/stylist/invitation/:id",
+            delete(stylist::revoke_invitation),
+        )
+        .route(
+            "/stylist/:id/branches",
+            put(stylist::update_stylist_branches),
+        )
+        .route("/stylist/:id", delete(stylist::remove_stylist))
+        .layer(manage_stylists);
+    let reservation_router = Router::new()
+        // Reservation
+        .route("/reservation", get(reservation::list_reservation))
+        .route("/reservation/:id", get(reservation::reservation_detail))
+        .route(
+            "/reservation/:id/confirm",
+            put(reservation::confirm_reservation),
+        )
+        .route(
+            "/reservation/:id/start",
+            put(reservation::start_reservation),
+        )
+        .route("/reservation/:id/done", put(reservation::done_reservation))
+        .route(
+            "/reservation/:id/no-show",
+            put(reservation::no_show_reservation),
+        )
+        .route(
+            "/reservation/:id/cancel",
+            put(reservation::cancel_reservation),
+        )
+        .route(
+            "/reservation/:id/history",
+            get(reservation::list_reservation_history),
+        )
         // .route("/salon/:salon_id", delete(salon::salon_user::delete_salon))
         // .route(
         //     "/salon/:salon_id/media",
@@ -40,17 +135,13 @@ pub fn salon_owner_router(db: Arc<Pool<Postgres>>) -> Router {
         //     "/salon/:salon_id/media/:media_id",
         //     delete(salon::salon_user::delete_salon_media),
         // )
-        // // Salon bed
-        // .route(
-        //     "/salon/:salon_id/salon-bed",
-        //     post(salon_bed::salon_user::create_salon_bed),
-        // )
-        // .route(
-        //     "/salon-bed/:salon_bed_id",
-        //     delete(salon_bed::salon_user::delete_salon_bed),
-        // )
+        .layer(manage_reservations);
+
+    Router::new()
+        .merge(salon_router)
+        .merge(stylist_router)
+        .merge(reservation_router)
         .with_state(db)
-        .layer(layer)
 }
 
 #[derive(OpenApi)]
@@ -60,15 +151,46 @@ pub fn salon_owner_router(db: Arc<Pool<Postgres>>) -> Router {
         salon::update_salon,
         salon_branch::add_branch,
         salon_branch::delete_branch,
+        salon_branch::list_opening_hours,
+        salon_branch::update_opening_hours,
+        salon_branch::list_closures,
+        salon_branch::add_closure,
+        salon_branch::delete_closure,
+        salon_branch::list_resources,
+        salon_branch::add_resource,
+        salon_branch::update_resource,
+        salon_branch::delete_resource,
         therapy::add_therapy,
         therapy::update_therapy,
         therapy::delete_therapy,
+        stylist::list_stylist,
+        stylist::list_invitation,
+        stylist::invite_stylist,
+        stylist::revoke_invitation,
+        stylist::update_stylist_branches,
+        stylist::remove_stylist,
+        reservation::list_reservation,
+        reservation::reservation_detail,
+        reservation::confirm_reservation,
+        reservation::start_reservation,
+        reservation::done_reservation,
+        reservation::no_show_reservation,
+        reservation::cancel_reservation,
+        reservation::list_reservation_history,
         ),
         components(
             schemas(
             UpdateSalonInput,
             AddSalonBranchInput,
+            OpeningHourInput,
+            UpdateOpeningHoursInput,
+            AddClosureInput,
+            AddBranchResourceInput,
+            UpdateBranchResourceInput,
             AddAndUpdateTherapyInput,
+            InviteStylistInput,
+            UpdateStylistBranchesInput,
+            CancelReservationInput,
         )
         ),
         modifiers(&SecurityAddon),
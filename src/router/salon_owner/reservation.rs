@@ -0,0 +1,352 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{FromRow, Pool, Postgres, Row};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    model::{
+        claim::Claims,
+        database::{Reservation, ReservationHistoryOutput, ReservationOutput, ReservationStatus},
+        error::AppError,
+        response::GeneralResponse,
+    },
+    utils::booking::{self, held_resources_join},
+};
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListReservationQueryInput {
+    pub salon_branch_id: Option<i64>,
+    pub therapy_id: Option<i64>,
+    pub stylist_id: Option<i64>,
+    pub status: Option<ReservationStatus>,
+    /// Reservations starting at or after this time
+    pub date_from: Option<DateTime<Utc>>,
+    /// Reservations starting before this time
+    pub date_to: Option<DateTime<Utc>>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const LIST_RESERVATION_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+COALESCE(to_jsonb(stylists) - 'password', 'null') as stylist,
+held.resources,
+COUNT(*) OVER () AS total
+FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+LEFT JOIN users stylists ON stylists.id = reservations.stylist_id
+",
+    held_resources_join!(),
+    "WHERE users.id = $1
+AND ($2::bigint IS NULL OR reservations.salon_branch_id = $2)
+AND ($3::bigint IS NULL OR reservations.therapy_id = $3)
+AND ($4::bigint IS NULL OR reservations.stylist_id = $4)
+AND ($5::reservation_status IS NULL OR reservations.status = $5)
+AND ($6::timestamptz IS NULL OR reservations.time_from >= $6)
+AND ($7::timestamptz IS NULL OR reservations.time_from < $7)
+ORDER BY reservations.time_from
+OFFSET $8
+LIMIT $9
+"
+);
+
+/// Get list of reservations made at salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/salon-owner/reservation",
+    security(("Authorization" = [])),
+    params(ListReservationQueryInput)
+)]
+pub async fn list_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Query(input): Query<ListReservationQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    let reservations = sqlx::query(LIST_RESERVATION_QUERY)
+        .bind(claims.id)
+        .bind(input.salon_branch_id)
+        .bind(input.therapy_id)
+        .bind(input.stylist_id)
+        .bind(input.status)
+        .bind(input.date_from)
+        .bind(input.date_to)
+        .bind(input.offset)
+        .bind(input.limit)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    let mut total: Option<i64> = None;
+    let reservations: Vec<ReservationOutput> = reservations
+        .into_iter()
+        .map(|reservation| {
+            if total.is_none() {
+                total = reservation.try_get("total").ok();
+            }
+            ReservationOutput::from_row(&reservation).unwrap_or_default()
+        })
+        .collect();
+
+    let total = total.unwrap_or(0);
+
+    let data = json!({
+        "reservations": reservations,
+        "total": total
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// -----------------------------------------------------------------------------
+
+const RESERVATION_DETAIL_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+COALESCE(to_jsonb(stylists) - 'password', 'null') as stylist,
+held.resources
+FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+LEFT JOIN users stylists ON stylists.id = reservations.stylist_id
+",
+    held_resources_join!(),
+    "WHERE users.id = $1
+AND reservations.id = $2
+"
+);
+
+/// Get reservation detail made at salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn reservation_detail(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let reservation: ReservationOutput = sqlx::query_as(RESERVATION_DETAIL_QUERY)
+        .bind(claims.id)
+        .bind(reservation_id)
+        .fetch_one(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(reservation)
+}
+
+// -----------------------------------------------------------------------------
+
+const OWNED_RESERVATION_QUERY: &str = "
+SELECT reservations.* FROM reservations
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $1
+AND reservations.id = $2
+FOR UPDATE OF reservations
+";
+
+/// Move a reservation of the salon of `owner_id` to `status`.
+async fn update_status(
+    db: &Pool<Postgres>,
+    owner_id: i64,
+    reservation_id: i64,
+    status: ReservationStatus,
+    reason: Option<String>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let reservation: Reservation = sqlx::query_as(OWNED_RESERVATION_QUERY)
+        .bind(owner_id)
+        .bind(reservation_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::not_found("reservation not found!"))?;
+
+    let started = reservation.time_from.is_some_and(|time| time <= Utc::now());
+    let needs_start = matches!(
+        status,
+        ReservationStatus::InProgress | ReservationStatus::Done | ReservationStatus::NoShow
+    );
+    if needs_start && !started {
+        return GeneralResponse::new_error("Reservation has not started yet!".to_string());
+    }
+
+    let reservation =
+        booking::transition_reservation(&mut tx, &reservation, status, owner_id, reason).await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(reservation)
+}
+
+/// Confirm reservation made at salon of salon owner
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/confirm",
+    security(("Authorization" = [])),
+)]
+pub async fn confirm_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    update_status(
+        db.as_ref(),
+        claims.id,
+        reservation_id,
+        ReservationStatus::Confirmed,
+        None,
+    )
+    .await
+}
+
+/// Start serving reservation made at salon of salon owner
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/start",
+    security(("Authorization" = [])),
+)]
+pub async fn start_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    update_status(
+        db.as_ref(),
+        claims.id,
+        reservation_id,
+        ReservationStatus::InProgress,
+        None,
+    )
+    .await
+}
+
+/// Mark reservation made at salon of salon owner as done
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/done",
+    security(("Authorization" = [])),
+)]
+pub async fn done_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    update_status(
+        db.as_ref(),
+        claims.id,
+        reservation_id,
+        ReservationStatus::Done,
+        None,
+    )
+    .await
+}
+
+/// Mark reservation made at salon of salon owner as no-show
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/no-show",
+    security(("Authorization" = [])),
+)]
+pub async fn no_show_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    update_status(
+        db.as_ref(),
+        claims.id,
+        reservation_id,
+        ReservationStatus::NoShow,
+        None,
+    )
+    .await
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct CancelReservationInput {
+    pub reason: String,
+}
+
+/// Cancel reservation made at salon of salon owner
+#[utoipa::path(
+    put,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/cancel",
+    security(("Authorization" = [])),
+)]
+pub async fn cancel_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+    Json(input): Json<CancelReservationInput>,
+) -> Result<GeneralResponse, AppError> {
+    update_status(
+        db.as_ref(),
+        claims.id,
+        reservation_id,
+        ReservationStatus::Cancel,
+        Some(input.reason),
+    )
+    .await
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_RESERVATION_HISTORY_QUERY: &str = "
+SELECT reservation_histories.*,
+to_jsonb(actors) - 'password' as actor
+FROM reservation_histories
+INNER JOIN reservations ON reservations.id = reservation_histories.reservation_id
+INNER JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+LEFT JOIN users actors ON actors.id = reservation_histories.actor_id
+WHERE users.id = $1
+AND reservations.id = $2
+ORDER BY reservation_histories.created_at, reservation_histories.id
+";
+
+/// Get status history of reservation made at salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/salon-owner/reservation/{id}/history",
+    security(("Authorization" = [])),
+)]
+pub async fn list_reservation_history(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let histories: Vec<ReservationHistoryOutput> = sqlx::query_as(LIST_RESERVATION_HISTORY_QUERY)
+        .bind(claims.id)
+        .bind(reservation_id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(histories)
+}
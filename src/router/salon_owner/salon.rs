@@ -5,26 +5,50 @@ use serde::{Deserialize, Serialize};
 use sqlx::{Pool, Postgres};
 use utoipa::ToSchema;
 
-use crate::model::{
-    claim::Claims,
-    database::{Salon, SalonDetailOutput},
-    error::AppError,
-    response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        database::{Salon, SalonDetailOutput},
+        error::AppError,
+        response::GeneralResponse,
+    },
+    utils::validation::Validator,
 };
 
 const SALON_DETAIL_QUERY: &str = "
 SELECT sl.*,
 COALESCE(
-  json_agg(DISTINCT br.*) FILTER (WHERE br.id IS NOT NULL),
-  '[]'::json
+  jsonb_agg(DISTINCT to_jsonb(br) || jsonb_build_object('opening_hours', oh.opening_hours))
+  FILTER (WHERE br.id IS NOT NULL),
+  '[]'::jsonb
 ) AS salon_branches,
 COALESCE(
   json_agg(DISTINCT tp.*) FILTER (WHERE tp.id IS NOT NULL),
   '[]'::json
-) AS therapies
+) AS therapies,
+(
+  SELECT COALESCE(json_agg(st ORDER BY st.id), '[]'::json) FROM (
+    SELECT stylists.id, stylists.username, stylists.full_name, stylists.email, stylists.avatar,
+    COALESCE(
+      array_agg(stylist_branches.salon_branch_id) FILTER (WHERE stylist_branches.salon_branch_id IS NOT NULL),
+      '{}'
+    ) AS salon_branch_ids
+    FROM users stylists
+    LEFT JOIN stylist_branches ON stylist_branches.stylist_id = stylists.id
+    WHERE stylists.salon_id = sl.id
+    AND stylists.role = 'STYLIST'
+    GROUP BY stylists.id
+  ) st
+) AS stylists
 FROM salons sl
 INNER JOIN users ur ON ur.salon_id = sl.id
 LEFT JOIN salon_branches br ON sl.id = br.salon_id
+LEFT JOIN LATERAL (
+  SELECT COALESCE(jsonb_agg(hours ORDER BY hours.day_of_week, hours.open_time), '[]'::jsonb)
+  AS opening_hours
+  FROM salon_branch_opening_hours hours
+  WHERE hours.salon_branch_id = br.id
+) oh ON true
 LEFT JOIN therapies tp ON sl.id = tp.salon_id
 WHERE ur.id = $1
 GROUP BY sl.id
@@ -63,10 +87,15 @@ pub struct UpdateSalonInput {
     pub phone: Option<String>,
     pub email: Option<String>,
     pub description: Option<String>,
+    /// Minimum minutes before a reservation starts for customers to cancel or reschedule it
+    pub cancellation_window_minutes: Option<i32>,
+    /// Only let customers with a verified email book
+    pub require_verified_email: Option<bool>,
     //pub salon_branches: Vec<UpdateSalonBranchInput>,
     //pub status: Option<GeneralStatus>,
 }
 
+#[allow(dead_code)]
 #[derive(ToSchema, Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(serialize = "camelCase"))]
 #[schema(rename_all = "camelCase")]
@@ -75,16 +104,34 @@ pub struct UpdateSalonBranchInput {
     pub address: Option<String>,
 }
 
+impl UpdateSalonInput {
+    fn validate(&self) -> Result<(), AppError> {
+        let window = self.cancellation_window_minutes.map(i64::from);
+        let mut validator = Validator::new();
+        // NOTE: The name is kept when left out, but can't be blanked
+        if self.name.is_some() {
+            validator.required("name", self.name.as_deref());
+        }
+        validator
+            .email("email", self.email.as_deref())
+            .phone("phone", self.phone.as_deref())
+            .non_negative("cancellationWindowMinutes", window)
+            .finish()
+    }
+}
+
 const UPDATE_SALON_QUERY: &str = "
 UPDATE salons SET
 logo = $1,
 cover_photo = $2,
-name = $3,
+name = COALESCE($3, name),
 phone = $4,
 email = $5,
-description = $6
+description = $6,
+cancellation_window_minutes = COALESCE($7, cancellation_window_minutes),
+require_verified_email = COALESCE($8, require_verified_email)
 FROM users
-WHERE users.id = $7 AND salons.id = users.salon_id
+WHERE users.id = $9 AND salons.id = users.salon_id
 RETURNING salons.*
 ";
 
@@ -100,6 +147,7 @@ pub async fn update_salon(
     Extension(claims): Extension<Claims>,
     Json(update_salon_input): Json<UpdateSalonInput>,
 ) -> Result<GeneralResponse, AppError> {
+    update_salon_input.validate()?;
     let salon: Salon = sqlx::query_as(UPDATE_SALON_QUERY)
         .bind(update_salon_input.logo)
         .bind(update_salon_input.cover_photo)
@@ -107,6 +155,8 @@ pub async fn update_salon(
         .bind(update_salon_input.phone)
         .bind(update_salon_input.email)
         .bind(update_salon_input.description)
+        .bind(update_salon_input.cancellation_window_minutes)
+        .bind(update_salon_input.require_verified_email)
         .bind(claims.id)
         .fetch_one(db.as_ref())
         .await?;
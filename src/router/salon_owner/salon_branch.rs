@@ -5,22 +5,33 @@ use axum::{
     http::StatusCode,
     Extension, Json,
 };
+use chrono::{DateTime, NaiveTime, Utc};
 use serde::Deserialize;
-use sqlx::{Pool, Postgres};
+use sqlx::{PgConnection, Pool, Postgres};
 use utoipa::ToSchema;
 
-use crate::model::{
-    claim::Claims, database::SalonBranch, error::AppError, response::GeneralResponse,
+use crate::{
+    model::{
+        claim::Claims,
+        database::{
+            BranchResource, ResourceType, SalonBranch, SalonBranchClosure, SalonBranchOpeningHour,
+        },
+        error::{AppError, ErrorCode},
+        response::GeneralResponse,
+    },
+    utils::{booking, validation::Validator},
 };
 
 const ADD_SALON_BRANCH_QUERY: &str = "
 INSERT INTO salon_branches (
   salon_id,
-  address
+  address,
+  time_zone
 )
 SELECT 
     users.salon_id,
-    $1
+    $1,
+    COALESCE($3, 'UTC')
 FROM
     users
 WHERE users.id = $2
@@ -32,6 +43,8 @@ RETURNING *
 #[schema(rename_all = "camelCase")]
 pub struct AddSalonBranchInput {
     address: String,
+    /// IANA time zone of the branch, UTC when missing
+    time_zone: Option<String>,
 }
 
 /// Add branch to salon of salon owner
@@ -49,9 +62,13 @@ pub async fn add_branch(
     Extension(claims): Extension<Claims>,
     Json(input): Json<AddSalonBranchInput>,
 ) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .time_zone("timeZone", input.time_zone.as_deref())
+        .finish()?;
     let branch: SalonBranch = sqlx::query_as(ADD_SALON_BRANCH_QUERY)
         .bind(input.address)
         .bind(claims.id)
+        .bind(input.time_zone)
         .fetch_one(db.as_ref())
         .await?;
 
@@ -69,7 +86,27 @@ AND salon_branches.id = $2
 RETURNING *;
 ";
 
-/// Delete branch salon of salon owner
+// NOTE: Deleting the branch would take the resources held by its reservations with it
+const HELD_BRANCH_RESOURCE_QUERY: &str = "
+SELECT EXISTS (
+  SELECT 1 FROM reservation_resources
+  INNER JOIN branch_resources ON branch_resources.id = reservation_resources.branch_resource_id
+  INNER JOIN reservations ON reservations.id = reservation_resources.reservation_id
+  WHERE branch_resources.salon_branch_id = $1
+  AND reservations.status IN ('WAITING', 'CONFIRMED', 'IN_PROGRESS')
+  AND reservations.time_to > now()
+)
+";
+
+// NOTE: Past reservations lose the branch anyway, so the units they held go with it
+const RELEASE_BRANCH_RESOURCE_QUERY: &str = "
+DELETE FROM reservation_resources
+USING branch_resources
+WHERE branch_resources.id = reservation_resources.branch_resource_id
+AND branch_resources.salon_branch_id = $1
+";
+
+/// Delete branch salon of salon owner, unless upcoming reservations hold its resources
 #[utoipa::path(
     delete,
     tag = "Salon branch",
@@ -84,11 +121,534 @@ pub async fn delete_branch(
     Extension(claims): Extension<Claims>,
     Path(branch_id): Path<i64>,
 ) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let _branch: SalonBranch = sqlx::query_as(OWNED_BRANCH_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    booking::lock_branch(&mut tx, branch_id).await?;
+
+    let held: bool = sqlx::query_scalar(HELD_BRANCH_RESOURCE_QUERY)
+        .bind(branch_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if held {
+        let message = "Resources of salon branch are held by upcoming reservations!".to_string();
+        return Err(AppError::Conflict(message));
+    }
+    sqlx::query(RELEASE_BRANCH_RESOURCE_QUERY)
+        .bind(branch_id)
+        .execute(&mut *tx)
+        .await?;
     let _branch: SalonBranch = sqlx::query_as(DELETE_BRANCH_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// -------------------------------------------------------------------------
+
+const OWNED_BRANCH_QUERY: &str = "
+SELECT salon_branches.* FROM salon_branches
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $1
+AND salon_branches.id = $2
+";
+
+const LIST_OPENING_HOURS_QUERY: &str = "
+SELECT * FROM salon_branch_opening_hours
+WHERE salon_branch_id = $1
+ORDER BY day_of_week, open_time
+";
+
+/// Get weekly opening hours of branch salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/hours",
+    security(("Authorization" = [])),
+)]
+pub async fn list_opening_hours(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let _branch: SalonBranch = sqlx::query_as(OWNED_BRANCH_QUERY)
         .bind(claims.id)
         .bind(branch_id)
         .fetch_one(db.as_ref())
         .await?;
 
+    let opening_hours: Vec<SalonBranchOpeningHour> = sqlx::query_as(LIST_OPENING_HOURS_QUERY)
+        .bind(branch_id)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    GeneralResponse::ok_with_data(opening_hours)
+}
+
+// -------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct OpeningHourInput {
+    /// Monday = 0, ..., Sunday = 6
+    pub day_of_week: i16,
+    /// Local time of the branch, e.g. "08:00:00"
+    #[schema(value_type = String)]
+    pub open_time: NaiveTime,
+    /// Local time of the branch, e.g. "20:00:00"
+    #[schema(value_type = String)]
+    pub close_time: NaiveTime,
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct UpdateOpeningHoursInput {
+    /// IANA time zone the hours are in, the current one of the branch when missing
+    pub time_zone: Option<String>,
+    pub opening_hours: Vec<OpeningHourInput>,
+}
+
+const UPDATE_TIME_ZONE_QUERY: &str = "
+UPDATE salon_branches SET time_zone = $2
+WHERE id = $1
+";
+
+const DELETE_OPENING_HOURS_QUERY: &str = "
+DELETE FROM salon_branch_opening_hours
+WHERE salon_branch_id = $1
+";
+
+const ADD_OPENING_HOURS_QUERY: &str = "
+INSERT INTO salon_branch_opening_hours (
+  salon_branch_id,
+  day_of_week,
+  open_time,
+  close_time
+)
+SELECT $1, * FROM UNNEST($2::smallint[], $3::time[], $4::time[])
+RETURNING *
+";
+
+/// Replace weekly opening hours of branch salon of salon owner
+#[utoipa::path(
+    put,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/hours",
+    security(("Authorization" = [])),
+)]
+pub async fn update_opening_hours(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+    Json(input): Json<UpdateOpeningHoursInput>,
+) -> Result<GeneralResponse, AppError> {
+    Validator::new()
+        .time_zone("timeZone", input.time_zone.as_deref())
+        .finish()?;
+    for hour in input.opening_hours.iter() {
+        if !(0..=6).contains(&hour.day_of_week) {
+            return GeneralResponse::new_error("dayOfWeek must be from 0 to 6!".to_string());
+        }
+        if hour.open_time >= hour.close_time {
+            let message = "openTime must be before closeTime!".to_string();
+            return Err(AppError::with_code(ErrorCode::InvalidTimeRange, message));
+        }
+    }
+
+    let mut tx = db.begin().await?;
+    let _branch: SalonBranch = sqlx::query_as(OWNED_BRANCH_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+    if let Some(time_zone) = input.time_zone {
+        sqlx::query(UPDATE_TIME_ZONE_QUERY)
+            .bind(branch_id)
+            .bind(time_zone)
+            .execute(&mut *tx)
+            .await?;
+    }
+    sqlx::query(DELETE_OPENING_HOURS_QUERY)
+        .bind(branch_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let days: Vec<i16> = input.opening_hours.iter().map(|h| h.day_of_week).collect();
+    let open_times: Vec<NaiveTime> = input.opening_hours.iter().map(|h| h.open_time).collect();
+    let close_times: Vec<NaiveTime> = input.opening_hours.iter().map(|h| h.close_time).collect();
+    let mut opening_hours: Vec<SalonBranchOpeningHour> = sqlx::query_as(ADD_OPENING_HOURS_QUERY)
+        .bind(branch_id)
+        .bind(days)
+        .bind(open_times)
+        .bind(close_times)
+        .fetch_all(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    opening_hours.sort_by_key(|hour| (hour.day_of_week, hour.open_time));
+    GeneralResponse::ok_with_data(opening_hours)
+}
+
+// -------------------------------------------------------------------------
+
+const LIST_CLOSURE_QUERY: &str = "
+SELECT * FROM salon_branch_closures
+WHERE salon_branch_id = $1
+AND time_to > now()
+ORDER BY time_from
+";
+
+/// Get upcoming closures of branch salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/closures",
+    security(("Authorization" = [])),
+)]
+pub async fn list_closures(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let _branch: SalonBranch = sqlx::query_as(OWNED_BRANCH_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(db.as_ref())
+        .await?;
+
+    let closures: Vec<SalonBranchClosure> = sqlx::query_as(LIST_CLOSURE_QUERY)
+        .bind(branch_id)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    GeneralResponse::ok_with_data(closures)
+}
+
+// -------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct AddClosureInput {
+    pub time_from: DateTime<Utc>,
+    pub time_to: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+const ADD_CLOSURE_QUERY: &str = "
+INSERT INTO salon_branch_closures (
+  salon_branch_id,
+  time_from,
+  time_to,
+  reason
+)
+SELECT
+    salon_branches.id,
+    $1,
+    $2,
+    $3
+FROM salon_branches
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $4
+AND salon_branches.id = $5
+RETURNING *
+";
+
+/// Add closure (holiday, renovation day, ...) to branch salon of salon owner
+#[utoipa::path(
+    post,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/closures",
+    security(("Authorization" = [])),
+)]
+pub async fn add_closure(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+    Json(input): Json<AddClosureInput>,
+) -> Result<GeneralResponse, AppError> {
+    if input.time_from >= input.time_to {
+        let message = "timeFrom must be before timeTo!".to_string();
+        return Err(AppError::with_code(ErrorCode::InvalidTimeRange, message));
+    }
+
+    let closure: SalonBranchClosure = sqlx::query_as(ADD_CLOSURE_QUERY)
+        .bind(input.time_from)
+        .bind(input.time_to)
+        .bind(input.reason)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(db.as_ref())
+        .await
+        .map_err(AppError::not_found("salon branch not found!"))?;
+
+    GeneralResponse::ok_with_data(closure)
+}
+
+// -------------------------------------------------------------------------
+
+const DELETE_CLOSURE_QUERY: &str = "
+DELETE FROM salon_branch_closures
+USING salon_branches, users
+WHERE salon_branches.id = salon_branch_closures.salon_branch_id
+AND users.salon_id = salon_branches.salon_id
+AND users.id = $1
+AND salon_branches.id = $2
+AND salon_branch_closures.id = $3
+RETURNING salon_branch_closures.*
+";
+
+/// Delete closure of branch salon of salon owner
+#[utoipa::path(
+    delete,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/closures/{closureId}",
+    security(("Authorization" = [])),
+)]
+pub async fn delete_closure(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path((branch_id, closure_id)): Path<(i64, i64)>,
+) -> Result<GeneralResponse, AppError> {
+    let _closure: SalonBranchClosure = sqlx::query_as(DELETE_CLOSURE_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .bind(closure_id)
+        .fetch_one(db.as_ref())
+        .await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// -------------------------------------------------------------------------
+
+const LIST_RESOURCE_QUERY: &str = "
+SELECT * FROM branch_resources
+WHERE salon_branch_id = $1
+AND deleted_at IS NULL
+ORDER BY id
+";
+
+/// Get resources (chairs, beds, ...) of branch salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/resources",
+    security(("Authorization" = [])),
+)]
+pub async fn list_resources(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let _branch: SalonBranch = sqlx::query_as(OWNED_BRANCH_QUERY)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(db.as_ref())
+        .await?;
+
+    let resources: Vec<BranchResource> = sqlx::query_as(LIST_RESOURCE_QUERY)
+        .bind(branch_id)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    GeneralResponse::ok_with_data(resources)
+}
+
+// -------------------------------------------------------------------------
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct AddBranchResourceInput {
+    pub resource_type: ResourceType,
+    pub name: String,
+    /// How many of this resource can be used at the same time
+    pub quantity: i32,
+}
+
+const ADD_RESOURCE_QUERY: &str = "
+INSERT INTO branch_resources (
+  salon_branch_id,
+  resource_type,
+  name,
+  quantity
+)
+SELECT
+    salon_branches.id,
+    $1,
+    $2,
+    $3
+FROM salon_branches
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $4
+AND salon_branches.id = $5
+RETURNING *
+";
+
+/// Add resource (chairs, beds, ...) to branch salon of salon owner
+#[utoipa::path(
+    post,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/resources",
+    security(("Authorization" = [])),
+)]
+pub async fn add_resource(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(branch_id): Path<i64>,
+    Json(input): Json<AddBranchResourceInput>,
+) -> Result<GeneralResponse, AppError> {
+    if input.quantity < 1 {
+        return GeneralResponse::new_error("quantity must be at least 1!".to_string());
+    }
+
+    let resource: BranchResource = sqlx::query_as(ADD_RESOURCE_QUERY)
+        .bind(input.resource_type)
+        .bind(input.name)
+        .bind(input.quantity)
+        .bind(claims.id)
+        .bind(branch_id)
+        .fetch_one(db.as_ref())
+        .await
+        .map_err(AppError::not_found("salon branch not found!"))?;
+
+    GeneralResponse::ok_with_data(resource)
+}
+
+// -------------------------------------------------------------------------
+
+const OWNED_RESOURCE_QUERY: &str = "
+SELECT branch_resources.* FROM branch_resources
+INNER JOIN salon_branches ON salon_branches.id = branch_resources.salon_branch_id
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $1
+AND salon_branches.id = $2
+AND branch_resources.id = $3
+AND branch_resources.deleted_at IS NULL
+FOR UPDATE OF branch_resources
+";
+
+// NOTE: Units from `$2` on are in use by reservations which are not over yet
+const UPCOMING_RESOURCE_USE_QUERY: &str = "
+SELECT COUNT(*) FROM reservation_resources
+INNER JOIN reservations ON reservations.id = reservation_resources.reservation_id
+WHERE reservation_resources.branch_resource_id = $1
+AND reservation_resources.unit >= $2
+AND reservations.status IN ('WAITING', 'CONFIRMED', 'IN_PROGRESS')
+AND reservations.time_to > now()
+";
+
+/// Lock a resource of branch salon of `owner_id` and make sure its units
+/// from `first_unit` on aren't held by upcoming reservations.
+async fn releasable_resource(
+    conn: &mut PgConnection,
+    owner_id: i64,
+    branch_id: i64,
+    resource_id: i64,
+    first_unit: i32,
+) -> Result<BranchResource, AppError> {
+    let resource: BranchResource = sqlx::query_as(OWNED_RESOURCE_QUERY)
+        .bind(owner_id)
+        .bind(branch_id)
+        .bind(resource_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(AppError::not_found("resource not found!"))?;
+    let in_use: i64 = sqlx::query_scalar(UPCOMING_RESOURCE_USE_QUERY)
+        .bind(resource_id)
+        .bind(first_unit)
+        .fetch_one(conn)
+        .await?;
+    if in_use > 0 {
+        let message = "Resource is still held by upcoming reservations!".to_string();
+        return Err(AppError::Conflict(message));
+    }
+    Ok(resource)
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct UpdateBranchResourceInput {
+    pub name: String,
+    pub quantity: i32,
+}
+
+const UPDATE_RESOURCE_QUERY: &str = "
+UPDATE branch_resources SET
+name = $1,
+quantity = $2
+WHERE id = $3
+RETURNING *
+";
+
+/// Update resource of branch salon of salon owner
+#[utoipa::path(
+    put,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/resources/{resourceId}",
+    security(("Authorization" = [])),
+)]
+pub async fn update_resource(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path((branch_id, resource_id)): Path<(i64, i64)>,
+    Json(input): Json<UpdateBranchResourceInput>,
+) -> Result<GeneralResponse, AppError> {
+    if input.quantity < 1 {
+        return GeneralResponse::new_error("quantity must be at least 1!".to_string());
+    }
+
+    let mut tx = db.begin().await?;
+    booking::lock_branch(&mut tx, branch_id).await?;
+    releasable_resource(&mut tx, claims.id, branch_id, resource_id, input.quantity).await?;
+    let resource: BranchResource = sqlx::query_as(UPDATE_RESOURCE_QUERY)
+        .bind(input.name)
+        .bind(input.quantity)
+        .bind(resource_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::ok_with_data(resource)
+}
+
+// -------------------------------------------------------------------------
+
+// NOTE: Past reservations still show the resources they held
+const DELETE_RESOURCE_QUERY: &str = "UPDATE branch_resources SET deleted_at = now() WHERE id = $1";
+
+/// Delete resource of branch salon of salon owner
+#[utoipa::path(
+    delete,
+    tag = "Salon branch",
+    path = "/salon-owner/salon/branch/{id}/resources/{resourceId}",
+    security(("Authorization" = [])),
+)]
+pub async fn delete_resource(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path((branch_id, resource_id)): Path<(i64, i64)>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    booking::lock_branch(&mut tx, branch_id).await?;
+    releasable_resource(&mut tx, claims.id, branch_id, resource_id, 0).await?;
+    sqlx::query(DELETE_RESOURCE_QUERY)
+        .bind(resource_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
     GeneralResponse::new_general(StatusCode::OK)
 }
@@ -0,0 +1,325 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Extension, Json,
+};
+use serde::Deserialize;
+use sqlx::{PgConnection, Pool, Postgres};
+use utoipa::ToSchema;
+
+use crate::model::{
+    claim::Claims,
+    database::{StylistInvitationOutput, StylistOutput, User},
+    error::AppError,
+    response::GeneralResponse,
+};
+
+const LIST_STYLIST_QUERY: &str = "
+SELECT stylists.id, stylists.username, stylists.full_name, stylists.email, stylists.avatar,
+COALESCE(
+  array_agg(stylist_branches.salon_branch_id ORDER BY stylist_branches.salon_branch_id)
+  FILTER (WHERE stylist_branches.salon_branch_id IS NOT NULL),
+  '{}'
+) AS salon_branch_ids
+FROM users stylists
+INNER JOIN users ON users.salon_id = stylists.salon_id
+LEFT JOIN stylist_branches ON stylist_branches.stylist_id = stylists.id
+WHERE users.id = $1
+AND stylists.role = 'STYLIST'
+GROUP BY stylists.id
+ORDER BY stylists.id
+";
+
+/// Get list of stylists of salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Stylist",
+    path = "/salon-owner/stylist",
+    security(("Authorization" = [])),
+)]
+pub async fn list_stylist(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let stylists: Vec<StylistOutput> = sqlx::query_as(LIST_STYLIST_QUERY)
+        .bind(claims.id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(stylists)
+}
+
+// -----------------------------------------------------------------------------
+
+const OWNED_BRANCH_COUNT_QUERY: &str = "
+SELECT COUNT(*) FROM salon_branches
+INNER JOIN users ON users.salon_id = salon_branches.salon_id
+WHERE users.id = $1
+AND salon_branches.id = ANY($2)
+";
+
+/// Make sure every branch of `salon_branch_ids` belongs to salon of `owner_id`.
+async fn check_owned_branches(
+    conn: &mut PgConnection,
+    owner_id: i64,
+    salon_branch_ids: &[i64],
+) -> Result<(), AppError> {
+    let mut branch_ids = salon_branch_ids.to_vec();
+    branch_ids.sort_unstable();
+    branch_ids.dedup();
+    let count: i64 = sqlx::query_scalar(OWNED_BRANCH_COUNT_QUERY)
+        .bind(owner_id)
+        .bind(&branch_ids)
+        .fetch_one(conn)
+        .await?;
+    if count != branch_ids.len() as i64 {
+        return Err(AppError::new("salon branch not found!".to_string()));
+    }
+    Ok(())
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct InviteStylistInput {
+    /// Username of the customer to invite
+    pub username: String,
+    /// Branches the stylist will work at after accepting
+    pub salon_branch_ids: Vec<i64>,
+}
+
+const INVITE_STYLIST_QUERY: &str = "
+INSERT INTO stylist_invitations (salon_id, user_id, salon_branch_ids)
+SELECT users.salon_id, invitees.id, $3
+FROM users, users invitees
+WHERE users.id = $1
+AND invitees.username = $2
+AND invitees.role = 'CUSTOMER'
+RETURNING *
+";
+
+/// Invite a customer to become stylist of salon of salon owner
+#[utoipa::path(
+    post,
+    tag = "Stylist",
+    path = "/salon-owner/stylist/invitation",
+    security(("Authorization" = [])),
+)]
+pub async fn invite_stylist(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Json(input): Json<InviteStylistInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut conn = db.acquire().await?;
+    check_owned_branches(&mut conn, claims.id, &input.salon_branch_ids).await?;
+
+    let invitation: StylistInvitationOutput = sqlx::query_as(INVITE_STYLIST_QUERY)
+        .bind(claims.id)
+        .bind(input.username)
+        .bind(input.salon_branch_ids)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or(AppError::NotFound("customer not found!".to_string()))?;
+    GeneralResponse::ok_with_data(invitation)
+}
+
+// -----------------------------------------------------------------------------
+
+const LIST_INVITATION_QUERY: &str = "
+SELECT stylist_invitations.*,
+to_jsonb(invitees) - 'password' as user
+FROM stylist_invitations
+INNER JOIN users ON users.salon_id = stylist_invitations.salon_id
+LEFT JOIN users invitees ON invitees.id = stylist_invitations.user_id
+WHERE users.id = $1
+AND stylist_invitations.status = 'PENDING'
+ORDER BY stylist_invitations.created_at DESC
+";
+
+/// Get pending stylist invitations of salon of salon owner
+#[utoipa::path(
+    get,
+    tag = "Stylist",
+    path = "/salon-owner/stylist/invitation",
+    security(("Authorization" = [])),
+)]
+pub async fn list_invitation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<GeneralResponse, AppError> {
+    let invitations: Vec<StylistInvitationOutput> = sqlx::query_as(LIST_INVITATION_QUERY)
+        .bind(claims.id)
+        .fetch_all(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(invitations)
+}
+
+// -----------------------------------------------------------------------------
+
+const REVOKE_INVITATION_QUERY: &str = "
+UPDATE stylist_invitations SET
+status = 'REVOKED',
+responded_at = now()
+FROM users
+WHERE users.salon_id = stylist_invitations.salon_id
+AND users.id = $1
+AND stylist_invitations.id = $2
+AND stylist_invitations.status = 'PENDING'
+RETURNING stylist_invitations.*
+";
+
+/// Revoke a pending stylist invitation of salon of salon owner
+#[utoipa::path(
+    delete,
+    tag = "Stylist",
+    path = "/salon-owner/stylist/invitation/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn revoke_invitation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(invitation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let invitation: Option<StylistInvitationOutput> = sqlx::query_as(REVOKE_INVITATION_QUERY)
+        .bind(claims.id)
+        .bind(invitation_id)
+        .fetch_optional(db.as_ref())
+        .await?;
+    match invitation {
+        Some(invitation) => GeneralResponse::ok_with_data(invitation),
+        None => Err(AppError::NotFound(
+            "Pending invitation not found!".to_string(),
+        )),
+    }
+}
+
+// -----------------------------------------------------------------------------
+
+const OWNED_STYLIST_QUERY: &str = "
+SELECT stylists.* FROM users stylists
+INNER JOIN users ON users.salon_id = stylists.salon_id
+WHERE users.id = $1
+AND stylists.id = $2
+AND stylists.role = 'STYLIST'
+FOR UPDATE OF stylists
+";
+
+/// Lock a stylist of salon of `owner_id`.
+async fn owned_stylist(
+    conn: &mut PgConnection,
+    owner_id: i64,
+    stylist_id: i64,
+) -> Result<User, AppError> {
+    let stylist = sqlx::query_as(OWNED_STYLIST_QUERY)
+        .bind(owner_id)
+        .bind(stylist_id)
+        .fetch_one(conn)
+        .await
+        .map_err(AppError::not_found("stylist not found!"))?;
+    Ok(stylist)
+}
+
+#[derive(ToSchema, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[schema(rename_all = "camelCase")]
+pub struct UpdateStylistBranchesInput {
+    pub salon_branch_ids: Vec<i64>,
+}
+
+const DELETE_STYLIST_BRANCHES_QUERY: &str = "
+DELETE FROM stylist_branches WHERE stylist_id = $1
+";
+
+const ADD_STYLIST_BRANCHES_QUERY: &str = "
+INSERT INTO stylist_branches (stylist_id, salon_branch_id)
+SELECT $1, salon_branches.id FROM salon_branches
+WHERE salon_branches.salon_id = $2
+AND salon_branches.id = ANY($3)
+ON CONFLICT DO NOTHING
+";
+
+/// Replace branches a stylist of salon of salon owner works at
+#[utoipa::path(
+    put,
+    tag = "Stylist",
+    path = "/salon-owner/stylist/{id}/branches",
+    security(("Authorization" = [])),
+)]
+pub async fn update_stylist_branches(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(stylist_id): Path<i64>,
+    Json(input): Json<UpdateStylistBranchesInput>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    let stylist = owned_stylist(&mut tx, claims.id, stylist_id).await?;
+    check_owned_branches(&mut tx, claims.id, &input.salon_branch_ids).await?;
+
+    sqlx::query(DELETE_STYLIST_BRANCHES_QUERY)
+        .bind(stylist_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(ADD_STYLIST_BRANCHES_QUERY)
+        .bind(stylist_id)
+        .bind(stylist.salon_id)
+        .bind(input.salon_branch_ids)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
+
+// -----------------------------------------------------------------------------
+
+const UPCOMING_RESERVATION_COUNT_QUERY: &str = "
+SELECT COUNT(*) FROM reservations
+WHERE stylist_id = $1
+AND status IN ('WAITING', 'CONFIRMED', 'IN_PROGRESS')
+AND time_to > now()
+";
+
+const REMOVE_STYLIST_QUERY: &str = "
+UPDATE users SET
+role = 'CUSTOMER',
+salon_id = NULL
+WHERE id = $1
+";
+
+/// Remove a stylist from salon of salon owner, the account becomes a customer again
+#[utoipa::path(
+    delete,
+    tag = "Stylist",
+    path = "/salon-owner/stylist/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn remove_stylist(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(stylist_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let mut tx = db.begin().await?;
+    owned_stylist(&mut tx, claims.id, stylist_id).await?;
+
+    let upcoming: i64 = sqlx::query_scalar(UPCOMING_RESERVATION_COUNT_QUERY)
+        .bind(stylist_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    if upcoming > 0 {
+        let message = "Stylist still has upcoming reservations!".to_string();
+        return Err(AppError::Conflict(message));
+    }
+
+    sqlx::query(DELETE_STYLIST_BRANCHES_QUERY)
+        .bind(stylist_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(REMOVE_STYLIST_QUERY)
+        .bind(stylist_id)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    GeneralResponse::new_general(StatusCode::OK)
+}
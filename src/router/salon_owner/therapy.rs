@@ -4,12 +4,19 @@ use axum::{
     extract::{Path, State},
     Extension, Json,
 };
-use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use sqlx::{Pool, Postgres};
 use utoipa::ToSchema;
 
-use crate::model::{claim::Claims, database::Therapy, error::AppError, response::GeneralResponse};
+use crate::{
+    model::{
+        claim::Claims,
+        database::{ResourceType, Therapy},
+        error::AppError,
+        response::GeneralResponse,
+    },
+    utils::{booking, validation::Validator},
+};
 
 #[derive(ToSchema, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
@@ -18,7 +25,33 @@ pub struct AddAndUpdateTherapyInput {
     pub name: Option<String>,
     pub description: Option<String>,
     pub price: Option<i64>,
-    pub duration: Option<DateTime<Utc>>,
+    /// How long the therapy takes, in minutes
+    pub duration_minutes: Option<i32>,
+    /// Cleanup time blocked after each reservation, in minutes
+    pub buffer_minutes: Option<i32>,
+    /// Branch resources held during the therapy, one of each listed type
+    pub resource_types: Option<Vec<ResourceType>>,
+}
+
+impl AddAndUpdateTherapyInput {
+    fn validate(&self) -> Result<(), AppError> {
+        Validator::new()
+            .required("name", self.name.as_deref())
+            .non_negative("price", self.price)
+            .positive("durationMinutes", self.duration_minutes.map(i64::from))
+            .at_most(
+                "durationMinutes",
+                self.duration_minutes.map(i64::from),
+                booking::MAX_THERAPY_DURATION_MINUTES,
+            )
+            .non_negative("bufferMinutes", self.buffer_minutes.map(i64::from))
+            .at_most(
+                "bufferMinutes",
+                self.buffer_minutes.map(i64::from),
+                booking::MAX_THERAPY_BUFFER_MINUTES,
+            )
+            .finish()
+    }
 }
 
 const ADD_THERAPY_QUERY: &str = "
@@ -27,9 +60,11 @@ salon_id,
 name,
 description,
 price,
-duration
-) select salon_id, $1, $2, $3, $4
-FROM users WHERE users.id = $5
+duration_minutes,
+buffer_minutes,
+resource_types
+) select salon_id, $1, $2, $3, $4, COALESCE($5, 0), COALESCE($6, '{}')
+FROM users WHERE users.id = $7
 RETURNING *
 ";
 
@@ -45,11 +80,14 @@ pub async fn add_therapy(
     Extension(claims): Extension<Claims>,
     Json(input): Json<AddAndUpdateTherapyInput>,
 ) -> Result<GeneralResponse, AppError> {
+    input.validate()?;
     let branch: Therapy = sqlx::query_as(ADD_THERAPY_QUERY)
         .bind(input.name)
         .bind(input.description)
         .bind(input.price)
-        .bind(input.duration)
+        .bind(input.duration_minutes)
+        .bind(input.buffer_minutes)
+        .bind(input.resource_types)
         .bind(claims.id)
         .fetch_one(db.as_ref())
         .await?;
@@ -65,11 +103,13 @@ UPDATE therapies SET
 name = $1,
 description = $2,
 price = $3,
-duration = $4
+duration_minutes = $4,
+buffer_minutes = COALESCE($5, 0),
+resource_types = COALESCE($6, '{}')
 FROM users
-WHERE users.id = $5
-AND therapies.id = $6
-AND therapies.id = users.salon_id
+WHERE users.id = $7
+AND therapies.id = $8
+AND therapies.salon_id = users.salon_id
 RETURNING therapies.*
 ";
 
@@ -86,11 +126,14 @@ pub async fn update_therapy(
     Path(therapy_id): Path<i64>,
     Json(input): Json<AddAndUpdateTherapyInput>,
 ) -> Result<GeneralResponse, AppError> {
+    input.validate()?;
     let branch: Therapy = sqlx::query_as(UPDATE_THERAPY_QUERY)
         .bind(input.name)
         .bind(input.description)
         .bind(input.price)
-        .bind(input.duration)
+        .bind(input.duration_minutes)
+        .bind(input.buffer_minutes)
+        .bind(input.resource_types)
         .bind(claims.id)
         .bind(therapy_id)
         .fetch_one(db.as_ref())
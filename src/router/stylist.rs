@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::{middleware, routing::get, Router};
+use sqlx::{Pool, Postgres};
+use utoipa::OpenApi;
+
+use crate::{
+    layer,
+    model::{api_doc::SecurityAddon, database::Permission},
+};
+
+mod reservation;
+
+pub fn stylist_router(db: Arc<Pool<Postgres>>) -> Router {
+    let layer = middleware::from_fn_with_state(
+        Permission::ViewAssignedReservations,
+        layer::permission_layer,
+    );
+    Router::new()
+        // Reservation
+        .route("/reservation", get(reservation::list_reservation))
+        .route("/reservation/:id", get(reservation::reservation_detail))
+        .with_state(db)
+        .layer(layer)
+}
+
+#[derive(OpenApi)]
+#[openapi(
+        paths(
+        reservation::list_reservation,
+        reservation::reservation_detail
+        ),
+        components(
+            schemas(
+        )
+        ),
+        modifiers(&SecurityAddon),
+    )]
+pub struct StylistApiDoc;
@@ -0,0 +1,141 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::{FromRow, Pool, Postgres, Row};
+use utoipa::IntoParams;
+
+use crate::{
+    model::{
+        claim::Claims,
+        database::{ReservationOutput, ReservationStatus},
+        error::AppError,
+        response::GeneralResponse,
+    },
+    utils::booking::held_resources_join,
+};
+
+#[derive(IntoParams, Deserialize, Debug, Clone)]
+#[serde(rename_all(deserialize = "camelCase"))]
+#[into_params(rename_all = "camelCase", parameter_in = Query)]
+pub struct ListReservationQueryInput {
+    pub status: Option<ReservationStatus>,
+    /// Reservations starting at or after this time
+    pub date_from: Option<DateTime<Utc>>,
+    /// Reservations starting before this time
+    pub date_to: Option<DateTime<Utc>>,
+    pub offset: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+const LIST_RESERVATION_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+held.resources,
+COUNT(*) OVER () AS total
+FROM reservations
+LEFT JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+",
+    held_resources_join!(),
+    "WHERE reservations.stylist_id = $1
+AND ($2::reservation_status IS NULL OR reservations.status = $2)
+AND ($3::timestamptz IS NULL OR reservations.time_from >= $3)
+AND ($4::timestamptz IS NULL OR reservations.time_from < $4)
+ORDER BY reservations.time_from
+OFFSET $5
+LIMIT $6
+"
+);
+
+/// Get list of reservations served by this stylist
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/stylist/reservation",
+    security(("Authorization" = [])),
+    params(ListReservationQueryInput)
+)]
+pub async fn list_reservation(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Query(input): Query<ListReservationQueryInput>,
+) -> Result<GeneralResponse, AppError> {
+    let reservations = sqlx::query(LIST_RESERVATION_QUERY)
+        .bind(claims.id)
+        .bind(input.status)
+        .bind(input.date_from)
+        .bind(input.date_to)
+        .bind(input.offset)
+        .bind(input.limit)
+        .fetch_all(db.as_ref())
+        .await?;
+
+    let mut total: Option<i64> = None;
+    let reservations: Vec<ReservationOutput> = reservations
+        .into_iter()
+        .map(|reservation| {
+            if total.is_none() {
+                total = reservation.try_get("total").ok();
+            }
+            ReservationOutput::from_row(&reservation).unwrap_or_default()
+        })
+        .collect();
+
+    let total = total.unwrap_or(0);
+
+    let data = json!({
+        "reservations": reservations,
+        "total": total
+    });
+    GeneralResponse::ok_with_data(data)
+}
+
+// -----------------------------------------------------------------------------
+
+const RESERVATION_DETAIL_QUERY: &str = concat!(
+    "
+SELECT reservations.*,
+to_jsonb(salon_branches) as salon_branch,
+to_jsonb(therapies) as therapy,
+to_jsonb(customers) - 'password' as customer,
+held.resources
+FROM reservations
+LEFT JOIN salon_branches ON salon_branches.id = reservations.salon_branch_id
+LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+LEFT JOIN users customers ON customers.id = reservations.user_id
+",
+    held_resources_join!(),
+    "WHERE reservations.stylist_id = $1
+AND reservations.id = $2
+"
+);
+
+/// Get detail of reservation served by this stylist
+#[utoipa::path(
+    get,
+    tag = "Reservation",
+    path = "/stylist/reservation/{id}",
+    security(("Authorization" = [])),
+)]
+pub async fn reservation_detail(
+    State(db): State<Arc<Pool<Postgres>>>,
+    Extension(claims): Extension<Claims>,
+    Path(reservation_id): Path<i64>,
+) -> Result<GeneralResponse, AppError> {
+    let reservation: ReservationOutput = sqlx::query_as(RESERVATION_DETAIL_QUERY)
+        .bind(claims.id)
+        .bind(reservation_id)
+        .fetch_one(db.as_ref())
+        .await?;
+    GeneralResponse::ok_with_data(reservation)
+}
@@ -1,4 +1,13 @@
-use anyhow::{anyhow, Context, Result};
+pub mod booking;
+pub mod email_verification;
+pub mod jwt_keys;
+pub mod mailer;
+pub mod password;
+pub mod session;
+pub mod sign_in_guard;
+pub mod token;
+pub mod two_factor;
+pub mod validation;
 
 //pub fn total_from_header(header: &HeaderMap) -> Result<usize> {
 //    let mut content_range = header
@@ -19,6 +28,7 @@ use anyhow::{anyhow, Context, Result};
 //    Ok((from_index, to_index))
 //}
 //
+#[allow(dead_code)]
 pub fn total_pages(total: i64, limit: i64) -> i64 {
     if total % limit != 0 {
         (total / limit) + 1
@@ -27,6 +37,7 @@ pub fn total_pages(total: i64, limit: i64) -> i64 {
     }
 }
 //
+#[allow(dead_code)]
 pub fn extract_page_and_limit(page: Option<i64>, limit: Option<i64>) -> (i64, i64) {
     let mut page = page.unwrap_or(1);
     if page <= 0 {
@@ -0,0 +1,923 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use sqlx::{PgConnection, PgExecutor};
+
+use crate::model::{
+    database::{
+        BranchResource, Reservation, ReservationStatus, ResourceType, SalonBranchOpeningHour,
+        Therapy,
+    },
+    error::{AppError, ErrorCode},
+    response::GeneralResponse,
+};
+
+// NOTE: Shared rules for computing when a salon branch can take a reservation
+
+/// Gap between two candidate start times offered to customers.
+pub const SLOT_STEP_MINUTES: i64 = 30;
+/// Length used when a therapy doesn't say how long it takes.
+pub const DEFAULT_THERAPY_DURATION_MINUTES: i64 = 60;
+/// Longest therapy salons can offer, so a search only has to look that far
+/// back for reservations still running.
+pub const MAX_THERAPY_DURATION_MINUTES: i64 = 12 * 60;
+pub const MAX_THERAPY_BUFFER_MINUTES: i64 = 4 * 60;
+/// Widest date range accepted by the availability search.
+pub const MAX_SEARCH_DAYS: i64 = 31;
+
+// NOTE: Branches without any configured opening hours are open every day between these,
+// local to the branch
+pub const DEFAULT_OPEN_TIME: NaiveTime = match NaiveTime::from_hms_opt(8, 0, 0) {
+    Some(time) => time,
+    None => panic!("invalid open time"),
+};
+pub const DEFAULT_CLOSE_TIME: NaiveTime = match NaiveTime::from_hms_opt(20, 0, 0) {
+    Some(time) => time,
+    None => panic!("invalid close time"),
+};
+
+/// One concrete chair, bed, ... of a branch resource.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ResourceUnit {
+    pub branch_resource_id: i64,
+    pub unit: i32,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BusyTime {
+    pub time_from: DateTime<Utc>,
+    pub time_to: DateTime<Utc>,
+    /// Stylist serving the reservation, `None` for closures and for
+    /// reservations made while the branch had no stylists.
+    #[sqlx(default)]
+    pub stylist_id: Option<i64>,
+    /// Resource units held by the reservation.
+    #[sqlx(default, json)]
+    pub resources: Vec<ResourceUnit>,
+}
+
+/// Who and what serves a reservation.
+#[derive(Debug, Clone, Default)]
+pub struct Allocation {
+    pub stylist_id: Option<i64>,
+    pub resources: Vec<ResourceUnit>,
+}
+
+/// Weekly opening hours, one-off closures, stylists and resources of a salon
+/// branch.
+#[derive(Debug, Clone, Default)]
+pub struct BranchSchedule {
+    /// Time zone the opening hours are local to
+    pub time_zone: Tz,
+    pub opening_hours: Vec<SalonBranchOpeningHour>,
+    pub closures: Vec<BusyTime>,
+    pub stylist_ids: Vec<i64>,
+    pub resources: Vec<BranchResource>,
+}
+
+impl BranchSchedule {
+    /// Opening ranges of the local `day` of the branch, in time order.
+    pub fn opening_ranges(&self, day: NaiveDate) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        if self.opening_hours.is_empty() {
+            let range = self
+                .local_time(day, DEFAULT_OPEN_TIME)
+                .zip(self.local_time(day, DEFAULT_CLOSE_TIME));
+            return range.into_iter().collect();
+        }
+        let day_of_week = day.weekday().num_days_from_monday() as i16;
+        let mut ranges: Vec<_> = self
+            .opening_hours
+            .iter()
+            .filter(|hour| hour.day_of_week == Some(day_of_week))
+            .filter_map(|hour| {
+                let open = self.local_time(day, hour.open_time?)?;
+                let close = self.local_time(day, hour.close_time?)?;
+                Some((open, close))
+            })
+            .collect();
+        ranges.sort();
+        ranges
+    }
+
+    /// `time` of the local `day` of the branch. Times skipped by a daylight
+    /// saving change move to the first time after the gap.
+    fn local_time(&self, day: NaiveDate, time: NaiveTime) -> Option<DateTime<Utc>> {
+        let local = day.and_time(time);
+        let resolve = |local: NaiveDateTime| self.time_zone.from_local_datetime(&local).earliest();
+        let time = resolve(local).or_else(|| {
+            (1..=4)
+                .map(|quarter| local + Duration::minutes(15 * quarter))
+                .find_map(resolve)
+        })?;
+        Some(time.with_timezone(&Utc))
+    }
+
+    /// Local date of the branch at `time`.
+    pub fn local_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        time.with_timezone(&self.time_zone).date_naive()
+    }
+
+    /// Whether `[from, to)` lies inside one opening range and outside every closure.
+    pub fn is_open(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> bool {
+        let in_opening_hours = self
+            .opening_ranges(self.local_date(from))
+            .iter()
+            .any(|(open, close)| from >= *open && to <= *close);
+        in_opening_hours && is_free(from, to, &self.closures)
+    }
+
+    /// Whether the branch has at least one resource of every type in `resource_types`.
+    pub fn has_resources(&self, resource_types: &[ResourceType]) -> bool {
+        resource_types.iter().all(|resource_type| {
+            self.resources
+                .iter()
+                .any(|resource| resource.resource_type == Some(*resource_type))
+        })
+    }
+
+    /// Pick the stylist (`requested_stylist_id` or the first free one) and the
+    /// lowest free unit of each of `resource_types` to serve `[from, to)`, or
+    /// `None` when something is fully booked by the `busy` times.
+    pub fn allocate(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        requested_stylist_id: Option<i64>,
+        resource_types: &[ResourceType],
+        busy: &[BusyTime],
+    ) -> Option<Allocation> {
+        let overlapping: Vec<&BusyTime> = busy
+            .iter()
+            .filter(|busy| to > busy.time_from && from < busy.time_to)
+            .collect();
+        // NOTE: Without stylists nor resources the branch serves one reservation at a time
+        if self.stylist_ids.is_empty() && resource_types.is_empty() {
+            return overlapping.is_empty().then(Allocation::default);
+        }
+        let stylist_id = match self.stylist_ids.is_empty() {
+            true => None,
+            false => Some(self.allocate_stylist(requested_stylist_id, &overlapping)?),
+        };
+        let resources = self.allocate_resources(resource_types, &overlapping)?;
+        Some(Allocation {
+            stylist_id,
+            resources,
+        })
+    }
+
+    fn allocate_stylist(
+        &self,
+        requested_stylist_id: Option<i64>,
+        overlapping: &[&BusyTime],
+    ) -> Option<i64> {
+        // NOTE: We can't tell who serves a reservation without stylist, so it blocks all of them
+        if overlapping.iter().any(|busy| busy.stylist_id.is_none()) {
+            return None;
+        }
+        let is_free = |id: i64| overlapping.iter().all(|busy| busy.stylist_id != Some(id));
+        match requested_stylist_id {
+            Some(id) => is_free(id).then_some(id),
+            None => self.stylist_ids.iter().copied().find(|id| is_free(*id)),
+        }
+    }
+
+    fn allocate_resources(
+        &self,
+        resource_types: &[ResourceType],
+        overlapping: &[&BusyTime],
+    ) -> Option<Vec<ResourceUnit>> {
+        let mut allocated: Vec<ResourceUnit> = Vec::new();
+        for resource_type in resource_types {
+            let unit = self
+                .resources
+                .iter()
+                .filter(|resource| resource.resource_type == Some(*resource_type))
+                .find_map(|resource| {
+                    let branch_resource_id = resource.id?;
+                    (0..resource.quantity.unwrap_or(0))
+                        .map(|unit| ResourceUnit {
+                            branch_resource_id,
+                            unit,
+                        })
+                        .find(|unit| {
+                            !allocated.contains(unit)
+                                && overlapping
+                                    .iter()
+                                    .all(|busy| !busy.resources.contains(unit))
+                        })
+                })?;
+            allocated.push(unit);
+        }
+        Some(allocated)
+    }
+}
+
+const BRANCH_TIME_ZONE_QUERY: &str = "SELECT time_zone FROM salon_branches WHERE id = $1";
+
+/// Time zone of the branch opening hours, UTC when unknown.
+pub async fn branch_time_zone<'e, E>(executor: E, salon_branch_id: i64) -> Result<Tz, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    let time_zone: Option<String> = sqlx::query_scalar(BRANCH_TIME_ZONE_QUERY)
+        .bind(salon_branch_id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(time_zone
+        .and_then(|time_zone| time_zone.parse().ok())
+        .unwrap_or_default())
+}
+
+const OPENING_HOURS_QUERY: &str = "
+SELECT * FROM salon_branch_opening_hours
+WHERE salon_branch_id = $1
+ORDER BY day_of_week, open_time
+";
+
+pub async fn opening_hours<'e, E>(
+    executor: E,
+    salon_branch_id: i64,
+) -> Result<Vec<SalonBranchOpeningHour>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as(OPENING_HOURS_QUERY)
+        .bind(salon_branch_id)
+        .fetch_all(executor)
+        .await
+}
+
+const CLOSURE_TIME_QUERY: &str = "
+SELECT time_from, time_to FROM salon_branch_closures
+WHERE salon_branch_id = $1
+AND time_from < $3
+AND time_to > $2
+";
+
+/// Closures of a branch overlapping `[from, to)`.
+pub async fn closure_times<'e, E>(
+    executor: E,
+    salon_branch_id: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<BusyTime>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as(CLOSURE_TIME_QUERY)
+        .bind(salon_branch_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(executor)
+        .await
+}
+
+pub fn therapy_duration(therapy: &Therapy) -> Duration {
+    let minutes = therapy
+        .duration_minutes
+        .map(i64::from)
+        .unwrap_or(DEFAULT_THERAPY_DURATION_MINUTES);
+    Duration::minutes(minutes)
+}
+
+pub fn therapy_buffer(therapy: &Therapy) -> Duration {
+    Duration::minutes(therapy.buffer_minutes.unwrap_or(0).into())
+}
+
+pub fn therapy_resource_types(therapy: &Therapy) -> &[ResourceType] {
+    therapy.resource_types.as_deref().unwrap_or_default()
+}
+
+const BRANCH_RESOURCE_QUERY: &str = "
+SELECT * FROM branch_resources
+WHERE salon_branch_id = $1
+AND deleted_at IS NULL
+ORDER BY id
+";
+
+pub async fn branch_resources<'e, E>(
+    executor: E,
+    salon_branch_id: i64,
+) -> Result<Vec<BranchResource>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as(BRANCH_RESOURCE_QUERY)
+        .bind(salon_branch_id)
+        .fetch_all(executor)
+        .await
+}
+
+/// `LEFT JOIN LATERAL` of the resource units held by `reservations`, with
+/// their type and name, as `held.resources`.
+macro_rules! held_resources_join {
+    () => {
+        "LEFT JOIN LATERAL (
+  SELECT COALESCE(
+    jsonb_agg(
+      jsonb_build_object(
+        'branch_resource_id', branch_resources.id,
+        'unit', reservation_resources.unit,
+        'resource_type', branch_resources.resource_type,
+        'name', branch_resources.name
+      ) ORDER BY branch_resources.id, reservation_resources.unit
+    ),
+    '[]'::jsonb
+  ) AS resources
+  FROM reservation_resources
+  INNER JOIN branch_resources ON branch_resources.id = reservation_resources.branch_resource_id
+  WHERE reservation_resources.reservation_id = reservations.id
+) held ON true
+"
+    };
+}
+pub(crate) use held_resources_join;
+
+const BRANCH_STYLIST_QUERY: &str = "
+SELECT stylist_branches.stylist_id FROM stylist_branches
+INNER JOIN users ON users.id = stylist_branches.stylist_id
+WHERE stylist_branches.salon_branch_id = $1
+AND users.role = 'STYLIST'
+AND users.deleted_at IS NULL
+AND users.suspended_at IS NULL
+ORDER BY stylist_branches.stylist_id
+";
+
+/// Stylists working at a branch who can take reservations, in id order.
+pub async fn branch_stylist_ids<'e, E>(
+    executor: E,
+    salon_branch_id: i64,
+) -> Result<Vec<i64>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_scalar(BRANCH_STYLIST_QUERY)
+        .bind(salon_branch_id)
+        .fetch_all(executor)
+        .await
+}
+
+// NOTE: A reservation keeps its seat until `time_to` plus the cleanup buffer of its therapy.
+// Stylists of the branch also get busy with their reservations at other branches.
+// Only reservations starting within the longest therapy before `from` can still be
+// running, which bounds the scan of the (salon_branch_id, time_from) index.
+const BUSY_TIME_QUERY: &str = "
+SELECT busy.* FROM (
+  SELECT reservations.time_from,
+  COALESCE(
+    reservations.time_to,
+    reservations.time_from + make_interval(mins => COALESCE(therapies.duration_minutes, $4))
+  ) + make_interval(mins => COALESCE(therapies.buffer_minutes, 0)) AS time_to,
+  reservations.stylist_id,
+  held.resources
+  FROM reservations
+  LEFT JOIN therapies ON therapies.id = reservations.therapy_id
+  LEFT JOIN LATERAL (
+    SELECT COALESCE(jsonb_agg(reservation_resources), '[]'::jsonb) AS resources
+    FROM reservation_resources
+    WHERE reservation_resources.reservation_id = reservations.id
+  ) held ON true
+  WHERE (
+    reservations.salon_branch_id = $1
+    OR reservations.stylist_id IN (
+      SELECT stylist_id FROM stylist_branches WHERE salon_branch_id = $1
+    )
+  )
+  AND reservations.time_from < $3
+  AND reservations.time_from > $2 - make_interval(mins => $6)
+  AND reservations.status IS DISTINCT FROM 'CANCEL'
+  AND reservations.id IS DISTINCT FROM $5
+) busy
+WHERE busy.time_to > $2
+";
+
+/// Reservations of a branch and its stylists (except cancelled ones and
+/// `exclude_reservation_id`) blocking any time in `[from, to)`.
+pub async fn busy_times<'e, E>(
+    executor: E,
+    salon_branch_id: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    exclude_reservation_id: Option<i64>,
+) -> Result<Vec<BusyTime>, sqlx::Error>
+where
+    E: PgExecutor<'e>,
+{
+    sqlx::query_as(BUSY_TIME_QUERY)
+        .bind(salon_branch_id)
+        .bind(from)
+        .bind(to)
+        .bind(DEFAULT_THERAPY_DURATION_MINUTES as i32)
+        .bind(exclude_reservation_id)
+        .bind((MAX_THERAPY_DURATION_MINUTES + MAX_THERAPY_BUFFER_MINUTES) as i32)
+        .fetch_all(executor)
+        .await
+}
+
+const LOCK_SALON_BRANCH_QUERY: &str = "
+SELECT id FROM salon_branches
+WHERE id = $1
+FOR UPDATE
+";
+
+// NOTE: Stylists are locked with advisory locks rather than their user rows, so booking
+// doesn't block profile updates nor sign-ins touching the users table. Always in key
+// order, so two branches sharing stylists can't deadlock
+const LOCK_BRANCH_STYLIST_QUERY: &str = "
+SELECT pg_advisory_xact_lock(lock_key) FROM (
+  SELECT DISTINCT hashtextextended('stylist_booking:' || stylist_id, 0) AS lock_key
+  FROM stylist_branches
+  WHERE salon_branch_id = $1
+  ORDER BY lock_key
+) AS stylists
+";
+
+/// Lock the branch and the bookings of its stylists until the end of the
+/// transaction, so concurrent bookings of them are checked one after another.
+pub async fn lock_branch(conn: &mut PgConnection, salon_branch_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(LOCK_SALON_BRANCH_QUERY)
+        .bind(salon_branch_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    sqlx::query(LOCK_BRANCH_STYLIST_QUERY)
+        .bind(salon_branch_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub enum Availability {
+    Available(Allocation),
+    Closed,
+    Unequipped,
+    Booked,
+}
+
+impl Availability {
+    /// Stylist and resources picked to serve an available time.
+    pub fn allocation(&self) -> Allocation {
+        match self {
+            Availability::Available(allocation) => allocation.clone(),
+            _ => Allocation::default(),
+        }
+    }
+
+    /// Error response explaining why the time can't be booked.
+    pub fn rejection(&self) -> Option<Result<GeneralResponse, AppError>> {
+        match self {
+            Availability::Available(_) => None,
+            Availability::Closed => Some(GeneralResponse::new_error_with_code(
+                ErrorCode::BranchClosed,
+                "Salon branch is not open at this time!".to_string(),
+            )),
+            Availability::Unequipped => Some(GeneralResponse::new_error_with_code(
+                ErrorCode::BranchUnequipped,
+                "Salon branch doesn't have resources this therapy needs!".to_string(),
+            )),
+            Availability::Booked => Some(GeneralResponse::new_error_with_code(
+                ErrorCode::SlotUnavailable,
+                "This time is already booked at the salon branch!".to_string(),
+            )),
+        }
+    }
+}
+
+/// Make sure `stylist_id`, if any, works at the branch of `schedule`.
+pub fn check_stylist(schedule: &BranchSchedule, stylist_id: Option<i64>) -> Result<(), AppError> {
+    match stylist_id {
+        Some(id) if !schedule.stylist_ids.contains(&id) => Err(AppError::with_code(
+            ErrorCode::StylistNotInBranch,
+            "stylist doesn't work at this salon branch!".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Make sure a reservation starting at `time_from` is still ahead of `now`.
+pub fn check_start_time(time_from: DateTime<Utc>, now: DateTime<Utc>) -> Result<(), AppError> {
+    if time_from <= now {
+        return Err(AppError::with_code(
+            ErrorCode::InvalidTimeRange,
+            "Reservation must start in the future!".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+const BOOKABLE_SALON_QUERY: &str = "
+SELECT salons.approved_at IS NOT NULL AND salons.status = 'ACTIVATE'
+FROM salon_branches
+INNER JOIN salons ON salons.id = salon_branches.salon_id
+WHERE salon_branches.id = $1
+FOR SHARE OF salons
+";
+
+/// Make sure the salon of the branch is approved and active, the only salons
+/// taking reservations. When booking, call it after [`lock_branch`] in the
+/// transaction which saves the reservation, so the salon can't be deactivated
+/// meanwhile.
+pub async fn check_salon_bookable<'e, E>(executor: E, salon_branch_id: i64) -> Result<(), AppError>
+where
+    E: PgExecutor<'e>,
+{
+    let bookable: Option<bool> = sqlx::query_scalar(BOOKABLE_SALON_QUERY)
+        .bind(salon_branch_id)
+        .fetch_optional(executor)
+        .await?;
+    if !bookable.unwrap_or(false) {
+        return Err(AppError::with_code(
+            ErrorCode::SalonUnavailable,
+            "Salon isn't taking reservations!".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Check whether `therapy` can start at `time_from` in the branch, served by
+/// `stylist_id` or any free stylist. Call it after [`lock_branch`] in the
+/// transaction which saves the reservation.
+pub async fn check_availability(
+    conn: &mut PgConnection,
+    salon_branch_id: i64,
+    therapy: &Therapy,
+    time_from: DateTime<Utc>,
+    stylist_id: Option<i64>,
+    exclude_reservation_id: Option<i64>,
+) -> Result<Availability, AppError> {
+    let time_to = time_from + therapy_duration(therapy);
+    let blocked_to = time_to + therapy_buffer(therapy);
+
+    let schedule = BranchSchedule {
+        time_zone: branch_time_zone(&mut *conn, salon_branch_id).await?,
+        opening_hours: opening_hours(&mut *conn, salon_branch_id).await?,
+        closures: closure_times(&mut *conn, salon_branch_id, time_from, time_to).await?,
+        stylist_ids: branch_stylist_ids(&mut *conn, salon_branch_id).await?,
+        resources: branch_resources(&mut *conn, salon_branch_id).await?,
+    };
+    check_stylist(&schedule, stylist_id)?;
+    if !schedule.is_open(time_from, time_to) {
+        return Ok(Availability::Closed);
+    }
+    let resource_types = therapy_resource_types(therapy);
+    if !schedule.has_resources(resource_types) {
+        return Ok(Availability::Unequipped);
+    }
+
+    let busy = busy_times(
+        &mut *conn,
+        salon_branch_id,
+        time_from,
+        blocked_to,
+        exclude_reservation_id,
+    )
+    .await?;
+    let allocation = schedule.allocate(time_from, blocked_to, stylist_id, resource_types, &busy);
+    let availability = match allocation {
+        Some(allocation) => Availability::Available(allocation),
+        None => Availability::Booked,
+    };
+    Ok(availability)
+}
+
+const DELETE_RESERVATION_RESOURCE_QUERY: &str = "
+DELETE FROM reservation_resources WHERE reservation_id = $1
+";
+
+const ADD_RESERVATION_RESOURCE_QUERY: &str = "
+INSERT INTO reservation_resources (reservation_id, branch_resource_id, unit)
+SELECT $1, * FROM UNNEST($2::bigint[], $3::int[])
+";
+
+/// Replace resource units held by reservation `reservation_id` with `resources`.
+pub async fn save_resources(
+    conn: &mut PgConnection,
+    reservation_id: i64,
+    resources: &[ResourceUnit],
+) -> Result<(), sqlx::Error> {
+    let (resource_ids, units): (Vec<i64>, Vec<i32>) = resources
+        .iter()
+        .map(|resource| (resource.branch_resource_id, resource.unit))
+        .unzip();
+    sqlx::query(DELETE_RESERVATION_RESOURCE_QUERY)
+        .bind(reservation_id)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(ADD_RESERVATION_RESOURCE_QUERY)
+        .bind(reservation_id)
+        .bind(resource_ids)
+        .bind(units)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub fn is_free(from: DateTime<Utc>, to: DateTime<Utc>, busy: &[BusyTime]) -> bool {
+    busy.iter()
+        .all(|busy| to <= busy.time_from || from >= busy.time_to)
+}
+
+/// Start times in `[date_from, date_to)` where `therapy` fits inside the
+/// branch `schedule` and, together with its cleanup buffer, can be served by
+/// `stylist_id` (or any stylist) and its resources without overlapping `busy`
+/// times.
+pub fn available_start_times(
+    date_from: DateTime<Utc>,
+    date_to: DateTime<Utc>,
+    therapy: &Therapy,
+    stylist_id: Option<i64>,
+    schedule: &BranchSchedule,
+    busy: &[BusyTime],
+) -> Vec<DateTime<Utc>> {
+    let duration = therapy_duration(therapy);
+    let buffer = therapy_buffer(therapy);
+    let resource_types = therapy_resource_types(therapy);
+    let now = Utc::now();
+    let step = Duration::minutes(SLOT_STEP_MINUTES);
+    let mut times = Vec::new();
+
+    let last_day = schedule.local_date(date_to);
+    for day in schedule.local_date(date_from).iter_days() {
+        if day > last_day {
+            break;
+        }
+        for (open, close) in schedule.opening_ranges(day) {
+            let mut start = open;
+            while start + duration <= close && start < date_to {
+                let end = start + duration;
+                if start >= date_from
+                    && start >= now
+                    && is_free(start, end, &schedule.closures)
+                    && schedule
+                        .allocate(start, end + buffer, stylist_id, resource_types, busy)
+                        .is_some()
+                {
+                    times.push(start);
+                }
+                start += step;
+            }
+        }
+    }
+    times
+}
+
+const ADD_HISTORY_QUERY: &str = "
+INSERT INTO reservation_histories
+(reservation_id, from_status, to_status, actor_id, reason)
+VALUES ($1, $2, $3, $4, $5)
+";
+
+/// Record a change of reservation `reservation_id` made by `actor_id`.
+pub async fn record_history(
+    conn: &mut PgConnection,
+    reservation_id: i64,
+    from_status: Option<ReservationStatus>,
+    to_status: ReservationStatus,
+    actor_id: i64,
+    reason: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(ADD_HISTORY_QUERY)
+        .bind(reservation_id)
+        .bind(from_status)
+        .bind(to_status)
+        .bind(actor_id)
+        .bind(reason)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+const ADD_RESCHEDULE_HISTORY_QUERY: &str = "
+INSERT INTO reservation_histories
+(reservation_id, action, from_status, to_status, rescheduled_from, rescheduled_to, actor_id)
+VALUES ($1, 'RESCHEDULE', $2, $2, $3, $4, $5)
+";
+
+/// Record that `actor_id` moved reservation `reservation_id` in `status` from
+/// start time `from` to `to`.
+pub async fn record_reschedule(
+    conn: &mut PgConnection,
+    reservation_id: i64,
+    status: ReservationStatus,
+    from: Option<DateTime<Utc>>,
+    to: DateTime<Utc>,
+    actor_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(ADD_RESCHEDULE_HISTORY_QUERY)
+        .bind(reservation_id)
+        .bind(status)
+        .bind(from)
+        .bind(to)
+        .bind(actor_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+const UPDATE_RESERVATION_STATUS_QUERY: &str = "
+UPDATE reservations SET
+status = $1,
+cancel_reason = $2,
+updated_at = now()
+WHERE id = $3
+RETURNING *
+";
+
+/// Move `reservation` to `next` status on behalf of `actor_id` and record it
+/// in the reservation history. The reservation row should be locked by the
+/// caller.
+pub async fn transition_reservation(
+    conn: &mut PgConnection,
+    reservation: &Reservation,
+    next: ReservationStatus,
+    actor_id: i64,
+    reason: Option<String>,
+) -> Result<Reservation, AppError> {
+    let reservation_id = reservation
+        .id
+        .ok_or(AppError::new("reservation id not found in db!".to_string()))?;
+    let current = reservation.status.unwrap_or(ReservationStatus::Waiting);
+    if !current.can_transition_to(next) {
+        let message = format!("Reservation can't be changed from {} to {}!", current, next);
+        return Err(AppError::with_code(
+            ErrorCode::InvalidStatusTransition,
+            message,
+        ));
+    }
+
+    let cancel_reason = match next {
+        ReservationStatus::Cancel => reason.clone(),
+        _ => reservation.cancel_reason.clone(),
+    };
+    let updated: Reservation = sqlx::query_as(UPDATE_RESERVATION_STATUS_QUERY)
+        .bind(next)
+        .bind(cancel_reason)
+        .bind(reservation_id)
+        .fetch_one(&mut *conn)
+        .await?;
+    record_history(conn, reservation_id, Some(current), next, actor_id, reason).await?;
+
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        // NOTE: Far ahead so no start time is dropped for being in the past
+        Utc.with_ymd_and_hms(2100, 3, 1, hour, minute, 0).unwrap()
+    }
+
+    fn schedule(stylist_ids: Vec<i64>, chairs: i32) -> BranchSchedule {
+        let resources = match chairs {
+            0 => vec![],
+            quantity => vec![BranchResource {
+                id: Some(10),
+                resource_type: Some(ResourceType::Chair),
+                quantity: Some(quantity),
+                ..Default::default()
+            }],
+        };
+        BranchSchedule {
+            stylist_ids,
+            resources,
+            ..Default::default()
+        }
+    }
+
+    fn busy(from: DateTime<Utc>, to: DateTime<Utc>, stylist_id: Option<i64>) -> BusyTime {
+        BusyTime {
+            time_from: from,
+            time_to: to,
+            stylist_id,
+            resources: vec![],
+        }
+    }
+
+    fn therapy(duration_minutes: i32, buffer_minutes: i32) -> Therapy {
+        Therapy {
+            duration_minutes: Some(duration_minutes),
+            buffer_minutes: Some(buffer_minutes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn allocate_picks_first_free_stylist() {
+        let schedule = schedule(vec![1, 2], 0);
+        let taken = [busy(at(9, 0), at(10, 0), Some(1))];
+        let allocation = schedule.allocate(at(9, 30), at(10, 30), None, &[], &taken);
+        assert_eq!(allocation.unwrap().stylist_id, Some(2));
+        let allocation = schedule.allocate(at(10, 0), at(11, 0), None, &[], &taken);
+        assert_eq!(allocation.unwrap().stylist_id, Some(1));
+    }
+
+    #[test]
+    fn allocate_respects_requested_stylist() {
+        let schedule = schedule(vec![1, 2], 0);
+        let taken = [busy(at(9, 0), at(10, 0), Some(1))];
+        assert!(schedule
+            .allocate(at(9, 0), at(10, 0), Some(1), &[], &taken)
+            .is_none());
+        let allocation = schedule.allocate(at(9, 0), at(10, 0), Some(2), &[], &taken);
+        assert_eq!(allocation.unwrap().stylist_id, Some(2));
+    }
+
+    #[test]
+    fn allocate_blocks_every_stylist_for_unassigned_reservation() {
+        let schedule = schedule(vec![1, 2], 0);
+        let taken = [busy(at(9, 0), at(10, 0), None)];
+        assert!(schedule
+            .allocate(at(9, 0), at(10, 0), None, &[], &taken)
+            .is_none());
+    }
+
+    #[test]
+    fn allocate_takes_lowest_free_resource_unit() {
+        let schedule = schedule(vec![], 2);
+        let chair = [ResourceType::Chair];
+        let mut taken = busy(at(9, 0), at(10, 0), None);
+        let allocation = schedule.allocate(at(9, 0), at(10, 0), None, &chair, &[]);
+        assert_eq!(allocation.unwrap().resources[0].unit, 0);
+
+        taken.resources = vec![ResourceUnit {
+            branch_resource_id: 10,
+            unit: 0,
+        }];
+        let allocation = schedule.allocate(at(9, 0), at(10, 0), None, &chair, &[taken.clone()]);
+        assert_eq!(allocation.unwrap().resources[0].unit, 1);
+
+        taken.resources.push(ResourceUnit {
+            branch_resource_id: 10,
+            unit: 1,
+        });
+        assert!(schedule
+            .allocate(at(9, 0), at(10, 0), None, &chair, &[taken])
+            .is_none());
+    }
+
+    #[test]
+    fn allocate_serves_one_reservation_at_a_time_without_staff() {
+        let schedule = schedule(vec![], 0);
+        let taken = [busy(at(9, 0), at(10, 0), None)];
+        assert!(schedule
+            .allocate(at(9, 30), at(10, 30), None, &[], &taken)
+            .is_none());
+        assert!(schedule
+            .allocate(at(10, 0), at(11, 0), None, &[], &taken)
+            .is_some());
+    }
+
+    #[test]
+    fn start_time_must_be_in_the_future() {
+        let now = at(12, 0);
+        assert!(check_start_time(at(12, 30), now).is_ok());
+        assert!(check_start_time(now, now).is_err());
+        assert!(check_start_time(at(11, 0), now).is_err());
+    }
+
+    #[test]
+    fn start_times_fit_inside_default_opening_hours() {
+        let schedule = schedule(vec![1], 0);
+        let times =
+            available_start_times(at(0, 0), at(23, 59), &therapy(60, 0), None, &schedule, &[]);
+        assert_eq!(times.first(), Some(&at(8, 0)));
+        assert_eq!(times.last(), Some(&at(19, 0)));
+        assert_eq!(times.len(), 23);
+    }
+
+    #[test]
+    fn start_times_skip_busy_stylist_and_buffer() {
+        let schedule = schedule(vec![1], 0);
+        let taken = [busy(at(10, 0), at(11, 0), Some(1))];
+        let times = available_start_times(
+            at(8, 0),
+            at(12, 0),
+            &therapy(60, 30),
+            None,
+            &schedule,
+            &taken,
+        );
+        // NOTE: 08:30 would end its buffer at 10:00, 09:00 and later overlap until 11:00
+        assert_eq!(times, vec![at(8, 0), at(8, 30), at(11, 0), at(11, 30)]);
+    }
+
+    #[test]
+    fn start_times_skip_closures() {
+        let mut schedule = schedule(vec![1], 0);
+        schedule.closures = vec![busy(at(8, 0), at(12, 0), None)];
+        let times =
+            available_start_times(at(8, 0), at(13, 0), &therapy(60, 0), None, &schedule, &[]);
+        assert_eq!(times, vec![at(12, 0), at(12, 30)]);
+    }
+
+    #[test]
+    fn start_times_follow_branch_time_zone() {
+        let mut schedule = schedule(vec![1], 0);
+        schedule.time_zone = chrono_tz::Asia::Ho_Chi_Minh;
+        let times =
+            available_start_times(at(0, 0), at(2, 0), &therapy(60, 0), None, &schedule, &[]);
+        // NOTE: 08:00 in Ho Chi Minh City is 01:00 UTC
+        assert_eq!(times, vec![at(1, 0), at(1, 30)]);
+    }
+}
@@ -0,0 +1,90 @@
+use chrono::{Duration, Utc};
+use sqlx::PgConnection;
+
+use super::{
+    mailer::{self, Mail},
+    token::{generate_token, hash_token},
+};
+use crate::model::{
+    database::User,
+    error::{AppError, ErrorCode},
+};
+
+const VERIFICATION_TOKEN_HOURS: i64 = 24;
+
+const ADD_VERIFICATION_TOKEN_QUERY: &str = "
+INSERT INTO email_verification_tokens (user_id, email, token_hash, expires_at)
+VALUES ($1, $2, $3, $4)
+";
+
+/// Save a verification token for `email` of account `user_id` and mail its
+/// link in the background.
+pub async fn send_verification(
+    conn: &mut PgConnection,
+    user_id: i64,
+    username: &str,
+    email: &str,
+) -> Result<(), AppError> {
+    let token = generate_token();
+    sqlx::query(ADD_VERIFICATION_TOKEN_QUERY)
+        .bind(user_id)
+        .bind(email)
+        .bind(hash_token(&token))
+        .bind(Utc::now() + Duration::hours(VERIFICATION_TOKEN_HOURS))
+        .execute(conn)
+        .await?;
+
+    let link = match mailer::frontend_link("verify-email", &token) {
+        Some(link) => format!("\nOr open {}\n", link),
+        None => String::new(),
+    };
+    let body = format!(
+        "Hi {},\n\nUse this token to verify your email address: {}\n{}\nIt expires in {} hours.\n",
+        username, token, link, VERIFICATION_TOKEN_HOURS
+    );
+    // NOTE: Sent in the background, so the request doesn't wait on the mail server
+    tokio::spawn(mailer::send_or_log(Mail {
+        to: email.to_string(),
+        subject: "Verify your email address".to_string(),
+        body,
+    }));
+    Ok(())
+}
+
+const USE_VERIFICATION_TOKEN_QUERY: &str = "
+UPDATE email_verification_tokens SET used_at = now()
+WHERE token_hash = $1
+AND used_at IS NULL
+AND expires_at > now()
+RETURNING user_id, email
+";
+
+// NOTE: The token only counts for the email it was sent to
+const VERIFY_EMAIL_QUERY: &str = "
+UPDATE users SET email_verified_at = now()
+WHERE id = $1
+AND email = $2
+RETURNING *
+";
+
+/// Mark the email a verification token was sent to as verified.
+pub async fn verify_email(conn: &mut PgConnection, token: &str) -> Result<User, AppError> {
+    let invalid_token = || {
+        AppError::with_code(
+            ErrorCode::InvalidVerificationToken,
+            "Verification token is invalid or expired!".to_string(),
+        )
+    };
+    let (user_id, email): (i64, String) = sqlx::query_as(USE_VERIFICATION_TOKEN_QUERY)
+        .bind(hash_token(token))
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(invalid_token)?;
+    let user = sqlx::query_as(VERIFY_EMAIL_QUERY)
+        .bind(user_id)
+        .bind(email)
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(invalid_token)?;
+    Ok(user)
+}
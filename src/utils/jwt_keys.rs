@@ -0,0 +1,175 @@
+use std::{env, str::FromStr, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use ring::{
+    rsa::{self, PublicKeyComponents},
+    signature::{Ed25519KeyPair, KeyPair},
+};
+
+/// Keys access tokens are signed and verified with.
+///
+/// With `JWT_SIGNING_KEY` (an Ed25519 or RSA private key in PEM) tokens are
+/// signed with EdDSA or RS256 and carry `JWT_SIGNING_KEY_ID` as `kid`. The
+/// public part of the signing key is published in the JWKS along with the keys
+/// of `JWT_VERIFICATION_KEYS`, a JWKS document of retired public keys still
+/// accepted. To rotate, move the current public key there, set the new signing
+/// key and drop the old one once its tokens expired.
+///
+/// Without `JWT_SIGNING_KEY` tokens fall back to HS256 with the `JWT_KEY`
+/// secret, which is never published.
+pub struct JwtKeys {
+    pub signing_key_id: Option<String>,
+    pub algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    verification_keys: Vec<VerificationKey>,
+    /// Public keys other services verify tokens with
+    pub jwks: JwkSet,
+}
+
+struct VerificationKey {
+    key_id: Option<String>,
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+}
+
+impl JwtKeys {
+    pub fn global() -> &'static JwtKeys {
+        static KEYS: OnceLock<JwtKeys> = OnceLock::new();
+        KEYS.get_or_init(|| JwtKeys::from_env().expect("invalid JWT key settings!"))
+    }
+
+    fn from_env() -> Result<Self> {
+        let Ok(pem) = env::var("JWT_SIGNING_KEY") else {
+            let secret = env::var("JWT_KEY").context("JWT_SIGNING_KEY or JWT_KEY must be set!")?;
+            return Ok(JwtKeys {
+                signing_key_id: None,
+                algorithm: Algorithm::HS256,
+                encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                verification_keys: vec![VerificationKey {
+                    key_id: None,
+                    algorithm: Algorithm::HS256,
+                    decoding_key: DecodingKey::from_secret(secret.as_bytes()),
+                }],
+                jwks: JwkSet { keys: Vec::new() },
+            });
+        };
+        let key_id = env::var("JWT_SIGNING_KEY_ID")
+            .context("JWT_SIGNING_KEY_ID must be set with JWT_SIGNING_KEY!")?;
+        let (algorithm, encoding_key, signing_jwk) = signing_key(&pem, &key_id)?;
+
+        let mut jwks = JwkSet {
+            keys: vec![signing_jwk],
+        };
+        if let Ok(keys) = env::var("JWT_VERIFICATION_KEYS") {
+            let retired: JwkSet =
+                serde_json::from_str(&keys).context("JWT_VERIFICATION_KEYS isn't a JWKS!")?;
+            jwks.keys.extend(retired.keys);
+        }
+        let verification_keys = jwks
+            .keys
+            .iter()
+            .map(verification_key)
+            .collect::<Result<_>>()?;
+
+        Ok(JwtKeys {
+            signing_key_id: Some(key_id),
+            algorithm,
+            encoding_key,
+            verification_keys,
+            jwks,
+        })
+    }
+
+    /// Key a token with header `kid` and `algorithm` has to be verified with.
+    pub fn decoding_key(&self, key_id: Option<&str>, algorithm: Algorithm) -> Option<&DecodingKey> {
+        self.verification_keys
+            .iter()
+            .find(|key| key.key_id.as_deref() == key_id && key.algorithm == algorithm)
+            .map(|key| &key.decoding_key)
+    }
+}
+
+fn public_jwk(key_id: &str, algorithm: KeyAlgorithm, parameters: AlgorithmParameters) -> Jwk {
+    Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(algorithm),
+            key_id: Some(key_id.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    }
+}
+
+/// Signing key from a PEM private key, with the JWK of its public part.
+fn signing_key(pem: &str, key_id: &str) -> Result<(Algorithm, EncodingKey, Jwk)> {
+    let parsed = pem::parse(pem).context("JWT_SIGNING_KEY isn't a PEM key!")?;
+    let der = parsed.contents();
+
+    if parsed.tag() == "PRIVATE KEY" {
+        if let Ok(key_pair) = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der) {
+            let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                key_type: OctetKeyPairType::OctetKeyPair,
+                curve: EllipticCurve::Ed25519,
+                x: URL_SAFE_NO_PAD.encode(key_pair.public_key().as_ref()),
+            });
+            return Ok((
+                Algorithm::EdDSA,
+                EncodingKey::from_ed_pem(pem.as_bytes())?,
+                public_jwk(key_id, KeyAlgorithm::EdDSA, parameters),
+            ));
+        }
+    }
+    let key_pair = match parsed.tag() {
+        "PRIVATE KEY" => rsa::KeyPair::from_pkcs8(der),
+        "RSA PRIVATE KEY" => rsa::KeyPair::from_der(der),
+        tag => bail!("unsupported JWT_SIGNING_KEY {}!", tag),
+    }
+    .map_err(|err| anyhow!("JWT_SIGNING_KEY isn't an Ed25519 or RSA key: {}", err))?;
+    let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+    let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(components.n),
+        e: URL_SAFE_NO_PAD.encode(components.e),
+    });
+    Ok((
+        Algorithm::RS256,
+        EncodingKey::from_rsa_pem(pem.as_bytes())?,
+        public_jwk(key_id, KeyAlgorithm::RS256, parameters),
+    ))
+}
+
+fn verification_key(jwk: &Jwk) -> Result<VerificationKey> {
+    let key_id = jwk
+        .common
+        .key_id
+        .clone()
+        .context("JWT verification keys need a kid!")?;
+    let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+        (AlgorithmParameters::OctetKey(_), _) => {
+            bail!(
+                "JWT verification key {} is a secret, not a public key!",
+                key_id
+            )
+        }
+        (_, Some(algorithm)) => Algorithm::from_str(&algorithm.to_string())?,
+        (AlgorithmParameters::OctetKeyPair(_), None) => Algorithm::EdDSA,
+        (AlgorithmParameters::RSA(_), None) => Algorithm::RS256,
+        (AlgorithmParameters::EllipticCurve(_), None) => {
+            bail!("JWT verification key {} needs an alg!", key_id)
+        }
+    };
+    Ok(VerificationKey {
+        key_id: Some(key_id),
+        algorithm,
+        decoding_key: DecodingKey::from_jwk(jwk)?,
+    })
+}
@@ -0,0 +1,140 @@
+use std::{env, sync::OnceLock};
+
+use anyhow::{bail, Context, Result};
+use axum::async_trait;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+const DEFAULT_SMTP_PORT: u16 = 25;
+const DEFAULT_MAIL_FROM: &str = "Hair Booking <no-reply@hair-booking.local>";
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Transport the application sends its emails through.
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<()>;
+}
+
+/// Sends emails to an SMTP server. Without `SMTP_TLS=true` the connection is
+/// plain, which suits local catchers such as MailHog.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn from_env(host: &str) -> Result<Self> {
+        let tls = env::var("SMTP_TLS").is_ok_and(|tls| tls == "true");
+        let mut builder = if tls {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host).port(DEFAULT_SMTP_PORT)
+        };
+        if let Some(port) = env::var("SMTP_PORT")
+            .ok()
+            .and_then(|port| port.parse().ok())
+        {
+            builder = builder.port(port);
+        }
+        if let (Ok(username), Ok(password)) = (env::var("SMTP_USERNAME"), env::var("SMTP_PASSWORD"))
+        {
+            builder = builder.credentials(Credentials::new(username, password));
+        }
+        let from = env::var("MAIL_FROM")
+            .unwrap_or(DEFAULT_MAIL_FROM.to_string())
+            .parse()?;
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for SmtpMailer {
+    async fn send(&self, mail: Mail) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(mail.to.parse()?)
+            .subject(mail.subject)
+            .body(mail.body)?;
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
+
+/// Prints emails instead of sending them, tokens included, used with
+/// `MAIL_TRANSPORT=log` for local development.
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, mail: Mail) -> Result<()> {
+        println!(
+            "Mail to {}\nSubject: {}\n\n{}",
+            mail.to, mail.subject, mail.body
+        );
+        Ok(())
+    }
+}
+
+/// Link to `path` of the front end carrying `token`, when `FRONTEND_URL` is set.
+pub fn frontend_link(path: &str, token: &str) -> Option<String> {
+    let url = env::var("FRONTEND_URL").ok()?;
+    Some(format!(
+        "{}/{}?token={}",
+        url.trim_end_matches('/'),
+        path,
+        token
+    ))
+}
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+/// Set up the mail transport, the SMTP server of `SMTP_HOST` or with
+/// `MAIL_TRANSPORT=log` the log. Without either, emails go to the log too.
+pub fn init_mailer() -> Result<()> {
+    let transport = env::var("MAIL_TRANSPORT").ok();
+    let host = env::var("SMTP_HOST").ok();
+    let mailer: Box<dyn Mailer> = match (transport.as_deref(), host) {
+        (Some("log"), _) => {
+            eprintln!("WARNING: MAIL_TRANSPORT=log prints emails and their tokens to the log!");
+            Box::new(LogMailer)
+        }
+        (None, None) => {
+            eprintln!("WARNING: SMTP_HOST is not set, emails and their tokens go to the log!");
+            Box::new(LogMailer)
+        }
+        (Some("smtp") | None, Some(host)) => {
+            Box::new(SmtpMailer::from_env(&host).context("invalid SMTP settings!")?)
+        }
+        (Some("smtp"), None) => bail!("SMTP_HOST must be set with MAIL_TRANSPORT=smtp!"),
+        (Some(transport), _) => bail!("unknown MAIL_TRANSPORT {}!", transport),
+    };
+    let _ = MAILER.set(mailer);
+    Ok(())
+}
+
+pub fn mailer() -> Result<&'static dyn Mailer> {
+    let mailer = MAILER.get().context("mailer is not initialized!")?;
+    Ok(mailer.as_ref())
+}
+
+/// Send `mail` and log failures, for emails the request shouldn't fail on.
+pub async fn send_or_log(mail: Mail) {
+    let to = mail.to.clone();
+    let sent = match mailer() {
+        Ok(mailer) => mailer.send(mail).await,
+        Err(err) => Err(err),
+    };
+    if let Err(err) = sent {
+        eprintln!("Failed to send mail to {}: {:?}", to, err);
+    }
+}
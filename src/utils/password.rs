@@ -0,0 +1,147 @@
+use std::{env, sync::OnceLock};
+
+use anyhow::{anyhow, bail, Result};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+
+use crate::model::error::AppError;
+
+const DEFAULT_BCRYPT_COST: u32 = 12;
+
+/// Scheme new passwords are hashed with, set by `PASSWORD_HASHER`
+/// (`bcrypt` or `argon2id`) and its cost variables.
+#[derive(Debug, Clone)]
+pub enum HashScheme {
+    /// `BCRYPT_COST`, 12 by default
+    Bcrypt { cost: u32 },
+    /// `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`,
+    /// OWASP's recommended minimums by default
+    Argon2id { params: Params },
+}
+
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("{} must be a number!", name)),
+        Err(_) => Ok(default),
+    }
+}
+
+static SCHEME: OnceLock<HashScheme> = OnceLock::new();
+
+impl HashScheme {
+    fn from_env() -> Result<Self> {
+        match env::var("PASSWORD_HASHER").as_deref() {
+            Ok("argon2id") => {
+                let params = Params::new(
+                    env_number("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST)?,
+                    env_number("ARGON2_ITERATIONS", Params::DEFAULT_T_COST)?,
+                    env_number("ARGON2_PARALLELISM", Params::DEFAULT_P_COST)?,
+                    None,
+                )
+                .map_err(|err| anyhow!("invalid Argon2 settings: {}!", err))?;
+                Ok(HashScheme::Argon2id { params })
+            }
+            Ok("bcrypt") | Err(_) => Ok(HashScheme::Bcrypt {
+                cost: env_number("BCRYPT_COST", DEFAULT_BCRYPT_COST)?,
+            }),
+            Ok(hasher) => bail!("unknown PASSWORD_HASHER {}!", hasher),
+        }
+    }
+
+    pub fn current() -> &'static HashScheme {
+        SCHEME.get_or_init(|| HashScheme::Bcrypt {
+            cost: DEFAULT_BCRYPT_COST,
+        })
+    }
+}
+
+fn argon2(params: Params) -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+}
+
+fn hash_blocking(password: &str) -> Result<String> {
+    match HashScheme::current() {
+        HashScheme::Bcrypt { cost } => Ok(bcrypt::hash(password, *cost)?),
+        HashScheme::Argon2id { params } => {
+            let salt = SaltString::generate(&mut OsRng);
+            let hash = argon2(params.clone())
+                .hash_password(password.as_bytes(), &salt)
+                .map_err(|err| anyhow!("failed to hash password: {}", err))?;
+            Ok(hash.to_string())
+        }
+    }
+}
+
+fn verify_blocking(password: &str, hash: &str) -> Result<bool> {
+    if hash.starts_with("$argon2") {
+        let Ok(parsed) = PasswordHash::new(hash) else {
+            return Ok(false);
+        };
+        return Ok(Argon2::default()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok());
+    }
+    Ok(bcrypt::verify(password, hash)?)
+}
+
+static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+
+/// Load the hash scheme from the environment and hash the dummy password with
+/// it, so bad settings fail at startup.
+pub async fn init() -> Result<()> {
+    let _ = SCHEME.set(HashScheme::from_env()?);
+    let dummy_hash = tokio::task::spawn_blocking(|| hash_blocking("dummy password")).await??;
+    let _ = DUMMY_HASH.set(dummy_hash);
+    Ok(())
+}
+
+/// Hash checked for unknown usernames so they take as long as wrong passwords.
+pub fn dummy_hash() -> &'static str {
+    DUMMY_HASH.get().map(String::as_str).unwrap_or_default()
+}
+
+/// Hash `password` with the current scheme. Hashing takes long on purpose, so
+/// it runs on the blocking threads instead of holding up other requests.
+pub async fn hash_password(password: &str) -> Result<String, AppError> {
+    let password = password.to_string();
+    let hash = tokio::task::spawn_blocking(move || hash_blocking(&password));
+    Ok(hash.await.map_err(anyhow::Error::from)??)
+}
+
+/// Check `password` against a stored hash of any supported scheme, on the
+/// blocking threads like [`hash_password`].
+pub async fn verify_password(password: &str, hash: &str) -> Result<bool, AppError> {
+    let (password, hash) = (password.to_string(), hash.to_string());
+    let valid = tokio::task::spawn_blocking(move || verify_blocking(&password, &hash));
+    Ok(valid.await.map_err(anyhow::Error::from)??)
+}
+
+/// Whether a stored hash was made with another scheme or cost than the
+/// current one, so it should be replaced after the next successful sign-in.
+pub fn needs_rehash(hash: &str) -> bool {
+    match HashScheme::current() {
+        HashScheme::Bcrypt { cost } => {
+            let hash_cost = hash
+                .strip_prefix("$2")
+                .and_then(|rest| rest.split('$').nth(1))
+                .and_then(|cost| cost.parse::<u32>().ok());
+            hash_cost != Some(*cost)
+        }
+        HashScheme::Argon2id { params } => {
+            let Ok(parsed) = PasswordHash::new(hash) else {
+                return true;
+            };
+            let hash_params = Params::try_from(&parsed).ok();
+            parsed.algorithm != Algorithm::Argon2id.ident()
+                || hash_params.is_none_or(|hash_params| {
+                    hash_params.m_cost() != params.m_cost()
+                        || hash_params.t_cost() != params.t_cost()
+                        || hash_params.p_cost() != params.p_cost()
+                })
+        }
+    }
+}
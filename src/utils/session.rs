@@ -0,0 +1,199 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgConnection, Row};
+
+use super::token::{generate_token, hash_token};
+use crate::model::{
+    client_info::ClientInfo,
+    database::{User, UserSession},
+    error::{AppError, ErrorCode},
+};
+
+/// Sessions stay alive this long after their last refresh.
+pub const SESSION_DAYS: i64 = 30;
+
+/// Refresh token handed to the client, only its hash is stored.
+pub struct RefreshToken {
+    pub session_id: i64,
+    pub user_id: i64,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Refuse accounts suspended by an admin.
+pub fn check_account(user: &User) -> Result<(), AppError> {
+    if user.suspended_at.is_none() {
+        return Ok(());
+    }
+    let message = match &user.suspended_reason {
+        Some(reason) => format!("Account is suspended: {}", reason),
+        None => "Account is suspended!".to_string(),
+    };
+    Err(AppError::with_code(ErrorCode::AccountSuspended, message))
+}
+
+fn invalid_refresh_token() -> AppError {
+    AppError::with_code(
+        ErrorCode::InvalidRefreshToken,
+        "Refresh token is invalid or expired!".to_string(),
+    )
+}
+
+const ADD_REFRESH_TOKEN_QUERY: &str = "
+INSERT INTO refresh_tokens (session_id, token_hash)
+VALUES ($1, $2)
+";
+
+async fn add_refresh_token(
+    conn: &mut PgConnection,
+    session: &UserSession,
+) -> Result<RefreshToken, AppError> {
+    let token = generate_token();
+    let session_id = session.id.unwrap_or_default();
+    sqlx::query(ADD_REFRESH_TOKEN_QUERY)
+        .bind(session_id)
+        .bind(hash_token(&token))
+        .execute(conn)
+        .await?;
+    Ok(RefreshToken {
+        session_id,
+        user_id: session.user_id.unwrap_or_default(),
+        token,
+        expires_at: session.expires_at.unwrap_or_default(),
+    })
+}
+
+const CREATE_SESSION_QUERY: &str = "
+INSERT INTO user_sessions (user_id, expires_at, user_agent, ip)
+VALUES ($1, $2, $3, $4)
+RETURNING *
+";
+
+/// Start a session for `user_id` on the `client` device and issue its first
+/// refresh token.
+pub async fn create_session(
+    conn: &mut PgConnection,
+    user_id: i64,
+    client: &ClientInfo,
+) -> Result<RefreshToken, AppError> {
+    let session: UserSession = sqlx::query_as(CREATE_SESSION_QUERY)
+        .bind(user_id)
+        .bind(Utc::now() + Duration::days(SESSION_DAYS))
+        .bind(client.user_agent.as_deref())
+        .bind(client.ip.as_deref())
+        .fetch_one(&mut *conn)
+        .await?;
+    add_refresh_token(conn, &session).await
+}
+
+const REFRESH_TOKEN_QUERY: &str = "
+SELECT session_id, used_at FROM refresh_tokens
+WHERE token_hash = $1
+FOR UPDATE
+";
+
+const USE_REFRESH_TOKEN_QUERY: &str = "
+UPDATE refresh_tokens SET used_at = now()
+WHERE token_hash = $1
+";
+
+const EXTEND_SESSION_QUERY: &str = "
+UPDATE user_sessions SET
+expires_at = $1,
+user_agent = COALESCE($3, user_agent),
+ip = COALESCE($4, ip),
+last_used_at = now()
+WHERE id = $2
+AND revoked_at IS NULL
+AND expires_at > now()
+RETURNING *
+";
+
+/// Swap `token` for a new refresh token of the same session. A token can be
+/// used once, presenting a used one again means it leaked, so its whole
+/// session is revoked. Commit the transaction even when this fails.
+pub async fn rotate_refresh_token(
+    conn: &mut PgConnection,
+    token: &str,
+    client: &ClientInfo,
+) -> Result<RefreshToken, AppError> {
+    let token_hash = hash_token(token);
+    let row = sqlx::query(REFRESH_TOKEN_QUERY)
+        .bind(&token_hash)
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(invalid_refresh_token)?;
+    let session_id: i64 = row.try_get("session_id")?;
+    let used_at: Option<DateTime<Utc>> = row.try_get("used_at")?;
+    if used_at.is_some() {
+        revoke_session(conn, session_id).await?;
+        return Err(invalid_refresh_token());
+    }
+
+    let session: UserSession = sqlx::query_as(EXTEND_SESSION_QUERY)
+        .bind(Utc::now() + Duration::days(SESSION_DAYS))
+        .bind(session_id)
+        .bind(client.user_agent.as_deref())
+        .bind(client.ip.as_deref())
+        .fetch_optional(&mut *conn)
+        .await?
+        .ok_or_else(invalid_refresh_token)?;
+    sqlx::query(USE_REFRESH_TOKEN_QUERY)
+        .bind(&token_hash)
+        .execute(&mut *conn)
+        .await?;
+    add_refresh_token(conn, &session).await
+}
+
+const SESSION_OF_TOKEN_QUERY: &str = "
+SELECT user_sessions.* FROM user_sessions
+INNER JOIN refresh_tokens ON refresh_tokens.session_id = user_sessions.id
+WHERE refresh_tokens.token_hash = $1
+AND user_sessions.revoked_at IS NULL
+";
+
+/// Live session a refresh token belongs to, used or not.
+pub async fn session_of_token(
+    conn: &mut PgConnection,
+    token: &str,
+) -> Result<Option<UserSession>, AppError> {
+    let session = sqlx::query_as(SESSION_OF_TOKEN_QUERY)
+        .bind(hash_token(token))
+        .fetch_optional(conn)
+        .await?;
+    Ok(session)
+}
+
+const REVOKE_SESSION_QUERY: &str = "
+UPDATE user_sessions SET revoked_at = now()
+WHERE id = $1
+AND revoked_at IS NULL
+";
+
+pub async fn revoke_session(conn: &mut PgConnection, session_id: i64) -> Result<(), AppError> {
+    sqlx::query(REVOKE_SESSION_QUERY)
+        .bind(session_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+const REVOKE_USER_SESSIONS_QUERY: &str = "
+UPDATE user_sessions SET revoked_at = now()
+WHERE user_id = $1
+AND revoked_at IS NULL
+AND id IS DISTINCT FROM $2
+";
+
+/// Revoke every session of `user_id`, except `keep_session_id` if given.
+pub async fn revoke_user_sessions(
+    conn: &mut PgConnection,
+    user_id: i64,
+    keep_session_id: Option<i64>,
+) -> Result<(), AppError> {
+    sqlx::query(REVOKE_USER_SESSIONS_QUERY)
+        .bind(user_id)
+        .bind(keep_session_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
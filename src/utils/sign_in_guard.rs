@@ -0,0 +1,166 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Connection, PgConnection};
+
+use crate::model::{
+    database::{SignInScope, SignInThrottle},
+    error::{AppError, ErrorCode},
+};
+
+/// Failures allowed before every further attempt has to wait.
+const FREE_FAILURES: i32 = 3;
+const MAX_DELAY_SECONDS: i64 = 60;
+/// Failures counted within this window, and how long a lock lasts.
+const WINDOW_MINUTES: i64 = 15;
+const USERNAME_LOCK_FAILURES: i32 = 10;
+// NOTE: Many accounts may share an IP, so it takes more failures to lock it
+const IP_LOCK_FAILURES: i32 = 50;
+
+/// When the next attempt is allowed after `throttle`'s failures.
+fn retry_at(throttle: &SignInThrottle) -> Option<DateTime<Utc>> {
+    let last_failed_at = throttle.last_failed_at?;
+    if last_failed_at < Utc::now() - Duration::minutes(WINDOW_MINUTES) {
+        return None;
+    }
+    let extra_failures = throttle.failed_count.unwrap_or_default() - FREE_FAILURES;
+    let delay = match extra_failures {
+        n if n <= 0 => None,
+        n => Some(last_failed_at + Duration::seconds((1i64 << n.min(6)).min(MAX_DELAY_SECONDS))),
+    };
+    delay.max(throttle.locked_until)
+}
+
+/// Throttled keys of an attempt with `username` from `ip`.
+fn throttle_keys<'a>(username: &'a str, ip: Option<&'a str>) -> Vec<(SignInScope, &'a str, i32)> {
+    let mut keys = vec![(SignInScope::Username, username, USERNAME_LOCK_FAILURES)];
+    if let Some(ip) = ip {
+        keys.push((SignInScope::Ip, ip, IP_LOCK_FAILURES));
+    }
+    keys
+}
+
+const ADD_THROTTLE_QUERY: &str = "
+INSERT INTO sign_in_throttles (scope, key, failed_count)
+VALUES ($1, $2, 0)
+ON CONFLICT (scope, key) DO NOTHING
+";
+
+// NOTE: Always in the same order, so concurrent attempts can't deadlock
+const LOCK_THROTTLES_QUERY: &str = "
+SELECT * FROM sign_in_throttles
+WHERE (scope = 'USERNAME' AND key = $1)
+OR (scope = 'IP' AND key = $2)
+ORDER BY scope, key
+FOR UPDATE
+";
+
+const COUNT_ATTEMPT_QUERY: &str = "
+UPDATE sign_in_throttles AS throttles SET
+failed_count = CASE
+  WHEN throttles.last_failed_at < now() - make_interval(mins => $3) THEN 1
+  ELSE throttles.failed_count + 1
+END,
+last_failed_at = now(),
+locked_until = CASE
+  WHEN throttles.last_failed_at >= now() - make_interval(mins => $3)
+  AND throttles.failed_count + 1 >= $4
+  THEN now() + make_interval(mins => $3)
+  ELSE throttles.locked_until
+END
+WHERE scope = $1
+AND key = $2
+";
+
+/// Refuse the attempt while `username` or `ip` has to wait after failures,
+/// otherwise count it as failed until [`release_attempt`] says it succeeded.
+/// Concurrent attempts are counted one after another, so they can't all pass
+/// the check before any failure is recorded.
+pub async fn reserve_attempt(
+    conn: &mut PgConnection,
+    username: &str,
+    ip: Option<&str>,
+) -> Result<(), AppError> {
+    let keys = throttle_keys(username, ip);
+    let mut tx = conn.begin().await?;
+    for (scope, key, _) in &keys {
+        sqlx::query(ADD_THROTTLE_QUERY)
+            .bind(scope)
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let throttles: Vec<SignInThrottle> = sqlx::query_as(LOCK_THROTTLES_QUERY)
+        .bind(username)
+        .bind(ip)
+        .fetch_all(&mut *tx)
+        .await?;
+    let retry_at = throttles.iter().filter_map(retry_at).max();
+    if let Some(retry_at) = retry_at.filter(|retry_at| *retry_at > Utc::now()) {
+        let seconds = (retry_at - Utc::now()).num_seconds() + 1;
+        let message = format!("Too many failed sign-ins, retry in {} seconds!", seconds);
+        return Err(AppError::with_code(ErrorCode::TooManyAttempts, message));
+    }
+    for (scope, key, lock_failures) in keys {
+        sqlx::query(COUNT_ATTEMPT_QUERY)
+            .bind(scope)
+            .bind(key)
+            .bind(WINDOW_MINUTES as i32)
+            .bind(lock_failures)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+// NOTE: A lock set by the released attempt is lifted with it
+const RELEASE_ATTEMPT_QUERY: &str = "
+UPDATE sign_in_throttles SET
+failed_count = GREATEST(failed_count - 1, 0),
+locked_until = CASE WHEN failed_count - 1 < $3 THEN NULL ELSE locked_until END
+WHERE scope = $1
+AND key = $2
+";
+
+const DELETE_UNUSED_THROTTLE_QUERY: &str = "
+DELETE FROM sign_in_throttles
+WHERE scope = $1
+AND key = $2
+AND failed_count = 0
+";
+
+/// Take back an attempt counted by [`reserve_attempt`] which didn't fail.
+pub async fn release_attempt(
+    conn: &mut PgConnection,
+    username: &str,
+    ip: Option<&str>,
+) -> Result<(), AppError> {
+    for (scope, key, lock_failures) in throttle_keys(username, ip) {
+        sqlx::query(RELEASE_ATTEMPT_QUERY)
+            .bind(scope)
+            .bind(key)
+            .bind(lock_failures)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query(DELETE_UNUSED_THROTTLE_QUERY)
+            .bind(scope)
+            .bind(key)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+const CLEAR_USERNAME_QUERY: &str = "
+DELETE FROM sign_in_throttles
+WHERE scope = 'USERNAME'
+AND key = $1
+";
+
+/// Forget failures of `username`, after it signed in or was unlocked by an admin.
+pub async fn clear_username(conn: &mut PgConnection, username: &str) -> Result<(), AppError> {
+    sqlx::query(CLEAR_USERNAME_QUERY)
+        .bind(username)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use std::{env, sync::OnceLock};
+
+use chrono::{Duration, Utc};
+use rand::RngCore;
+use sqlx::PgConnection;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use super::token::{generate_token, hash_token};
+use crate::model::{
+    database::{User, UserRole},
+    error::{AppError, ErrorCode},
+};
+
+const DEFAULT_ISSUER: &str = "Hair Booking";
+const SECRET_BYTES: usize = 20;
+const CODE_DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+/// Codes of this many steps before or after now are accepted, for clock drift.
+const ALLOWED_DRIFT_STEPS: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_BYTES: usize = 5;
+/// Time between the password step and the code step of a sign-in.
+const CHALLENGE_MINUTES: i64 = 5;
+
+static REQUIRE_ADMIN_2FA: OnceLock<bool> = OnceLock::new();
+
+/// Load whether admins must turn on two-factor sign-in, `REQUIRE_ADMIN_2FA`
+/// being `true` or `false` (the default).
+pub fn init() -> anyhow::Result<()> {
+    let required = match env::var("REQUIRE_ADMIN_2FA").as_deref() {
+        Ok("true") => true,
+        Ok("false") | Err(_) => false,
+        Ok(value) => anyhow::bail!("invalid REQUIRE_ADMIN_2FA {}!", value),
+    };
+    let _ = REQUIRE_ADMIN_2FA.set(required);
+    Ok(())
+}
+
+/// Whether accounts of `role` must turn on two-factor sign-in.
+pub fn required_for(role: Option<UserRole>) -> bool {
+    role == Some(UserRole::Admin) && REQUIRE_ADMIN_2FA.get().is_some_and(|required| *required)
+}
+
+/// New random secret, base32 encoded the way authenticator apps take it.
+pub fn new_secret() -> String {
+    let mut bytes = vec![0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    Secret::Raw(bytes).to_encoded().to_string()
+}
+
+fn totp(secret: &str, username: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|err| AppError::new(format!("invalid TOTP secret: {:?}", err)))?;
+    let issuer = env::var("TOTP_ISSUER").unwrap_or(DEFAULT_ISSUER.to_string());
+    // NOTE: Unchecked since usernames may contain ':', which is escaped in the URL anyway
+    Ok(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        CODE_DIGITS,
+        0,
+        STEP_SECONDS,
+        secret,
+        Some(issuer),
+        username.to_string(),
+    ))
+}
+
+/// `otpauth://` URI to show as a QR code for authenticator apps.
+pub fn provisioning_url(secret: &str, username: &str) -> Result<String, AppError> {
+    Ok(totp(secret, username)?.get_url())
+}
+
+const USE_STEP_QUERY: &str = "
+UPDATE users SET totp_last_step = $2
+WHERE id = $1
+AND (totp_last_step IS NULL OR totp_last_step < $2)
+RETURNING id
+";
+
+/// Check a TOTP `code` against the secret of `user`. A code is accepted once,
+/// along with every code of earlier steps.
+pub async fn verify_code(
+    conn: &mut PgConnection,
+    user: &User,
+    code: &str,
+) -> Result<bool, AppError> {
+    let Some(secret) = &user.totp_secret else {
+        return Ok(false);
+    };
+    let totp = totp(secret, &user.username.clone().unwrap_or_default())?;
+    let current_step = Utc::now().timestamp() as u64 / STEP_SECONDS;
+    let step = (current_step - ALLOWED_DRIFT_STEPS..=current_step + ALLOWED_DRIFT_STEPS)
+        .find(|step| totp.check(code.trim(), step * STEP_SECONDS));
+    let Some(step) = step else {
+        return Ok(false);
+    };
+    let used: Option<i64> = sqlx::query_scalar(USE_STEP_QUERY)
+        .bind(user.id)
+        .bind(step as i64)
+        .fetch_optional(conn)
+        .await?;
+    Ok(used.is_some())
+}
+
+const DROP_RECOVERY_CODES_QUERY: &str = "
+DELETE FROM two_factor_recovery_codes
+WHERE user_id = $1
+";
+
+const ADD_RECOVERY_CODE_QUERY: &str = "
+INSERT INTO two_factor_recovery_codes (user_id, code_hash)
+VALUES ($1, $2)
+";
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.trim().replace('-', "").to_lowercase()
+}
+
+/// Replace the recovery codes of `user_id` with new ones, returned once in
+/// plain text.
+pub async fn generate_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: i64,
+) -> Result<Vec<String>, AppError> {
+    drop_recovery_codes(conn, user_id).await?;
+    let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+    for _ in 0..RECOVERY_CODE_COUNT {
+        let mut bytes = [0u8; RECOVERY_CODE_BYTES];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let code = hex::encode(bytes);
+        sqlx::query(ADD_RECOVERY_CODE_QUERY)
+            .bind(user_id)
+            .bind(hash_token(&code))
+            .execute(&mut *conn)
+            .await?;
+        codes.push(format!("{}-{}", &code[..5], &code[5..]));
+    }
+    Ok(codes)
+}
+
+pub async fn drop_recovery_codes(conn: &mut PgConnection, user_id: i64) -> Result<(), AppError> {
+    sqlx::query(DROP_RECOVERY_CODES_QUERY)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+const USE_RECOVERY_CODE_QUERY: &str = "
+UPDATE two_factor_recovery_codes SET used_at = now()
+WHERE user_id = $1
+AND code_hash = $2
+AND used_at IS NULL
+RETURNING id
+";
+
+/// Spend one of the recovery codes of `user_id`.
+pub async fn use_recovery_code(
+    conn: &mut PgConnection,
+    user_id: i64,
+    code: &str,
+) -> Result<bool, AppError> {
+    let used: Option<i64> = sqlx::query_scalar(USE_RECOVERY_CODE_QUERY)
+        .bind(user_id)
+        .bind(hash_token(&normalize_recovery_code(code)))
+        .fetch_optional(conn)
+        .await?;
+    Ok(used.is_some())
+}
+
+const RECOVERY_CODES_LEFT_QUERY: &str = "
+SELECT count(*) FROM two_factor_recovery_codes
+WHERE user_id = $1
+AND used_at IS NULL
+";
+
+pub async fn recovery_codes_left(conn: &mut PgConnection, user_id: i64) -> Result<i64, AppError> {
+    let count = sqlx::query_scalar(RECOVERY_CODES_LEFT_QUERY)
+        .bind(user_id)
+        .fetch_one(conn)
+        .await?;
+    Ok(count)
+}
+
+/// Check the second factor of `user`, either a TOTP `code` or a `recovery_code`.
+pub async fn verify_second_factor(
+    conn: &mut PgConnection,
+    user: &User,
+    code: Option<&str>,
+    recovery_code: Option<&str>,
+) -> Result<(), AppError> {
+    let is_valid = match (code, recovery_code) {
+        (Some(code), _) => verify_code(conn, user, code).await?,
+        (None, Some(recovery_code)) => {
+            use_recovery_code(conn, user.id.unwrap_or_default(), recovery_code).await?
+        }
+        (None, None) => false,
+    };
+    if !is_valid {
+        let message = "Two-factor code is wrong or already used!".to_string();
+        return Err(AppError::with_code(
+            ErrorCode::InvalidTwoFactorCode,
+            message,
+        ));
+    }
+    Ok(())
+}
+
+const ADD_CHALLENGE_QUERY: &str = "
+INSERT INTO two_factor_challenges (user_id, token_hash, expires_at)
+VALUES ($1, $2, $3)
+";
+
+/// Token standing for a sign-in of `user_id` whose password was right, to be
+/// finished with a second factor.
+pub async fn create_challenge(conn: &mut PgConnection, user_id: i64) -> Result<String, AppError> {
+    let token = generate_token();
+    sqlx::query(ADD_CHALLENGE_QUERY)
+        .bind(user_id)
+        .bind(hash_token(&token))
+        .bind(Utc::now() + Duration::minutes(CHALLENGE_MINUTES))
+        .execute(conn)
+        .await?;
+    Ok(token)
+}
+
+fn invalid_challenge() -> AppError {
+    AppError::with_code(
+        ErrorCode::InvalidTwoFactorChallenge,
+        "Sign-in challenge is invalid or expired, sign in again!".to_string(),
+    )
+}
+
+const CHALLENGE_USER_QUERY: &str = "
+SELECT users.* FROM two_factor_challenges
+INNER JOIN users ON users.id = two_factor_challenges.user_id
+WHERE two_factor_challenges.token_hash = $1
+AND two_factor_challenges.used_at IS NULL
+AND two_factor_challenges.expires_at > now()
+AND users.deleted_at IS NULL
+FOR UPDATE OF two_factor_challenges
+";
+
+/// User signing in with a pending challenge `token`, locking the challenge
+/// until the end of the transaction.
+pub async fn challenge_user(conn: &mut PgConnection, token: &str) -> Result<User, AppError> {
+    sqlx::query_as(CHALLENGE_USER_QUERY)
+        .bind(hash_token(token))
+        .fetch_optional(conn)
+        .await?
+        .ok_or_else(invalid_challenge)
+}
+
+const USE_CHALLENGE_QUERY: &str = "
+UPDATE two_factor_challenges SET used_at = now()
+WHERE token_hash = $1
+AND used_at IS NULL
+RETURNING id
+";
+
+/// Mark a challenge as finished so it can't start another session.
+pub async fn finish_challenge(conn: &mut PgConnection, token: &str) -> Result<(), AppError> {
+    let used: Option<i64> = sqlx::query_scalar(USE_CHALLENGE_QUERY)
+        .bind(hash_token(token))
+        .fetch_optional(conn)
+        .await?;
+    used.map(|_| ()).ok_or_else(invalid_challenge)
+}
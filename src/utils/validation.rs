@@ -0,0 +1,254 @@
+use chrono_tz::Tz;
+
+use crate::model::error::{AppError, FieldError, FieldErrorCode};
+
+const MIN_PHONE_DIGITS: usize = 8;
+const MAX_PHONE_DIGITS: usize = 15;
+const MIN_PASSWORD_LENGTH: usize = 8;
+// NOTE: bcrypt ignores anything after 72 bytes
+const MAX_PASSWORD_BYTES: usize = 72;
+
+/// Collects field errors of an input so they can be sent all at once,
+/// before any query runs.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(&mut self, field: &str, code: FieldErrorCode, message: String) {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            code,
+            message,
+        });
+    }
+
+    /// `value` must be present and not blank.
+    pub fn required(&mut self, field: &str, value: Option<&str>) -> &mut Self {
+        if value.is_none_or(|value| value.trim().is_empty()) {
+            self.add(
+                field,
+                FieldErrorCode::Required,
+                format!("{} is required!", field),
+            );
+        }
+        self
+    }
+
+    /// `value`, if any, must look like an email address.
+    pub fn email(&mut self, field: &str, value: Option<&str>) -> &mut Self {
+        if value.is_some_and(|value| !is_email(value)) {
+            let message = format!("{} must be a valid email address!", field);
+            self.add(field, FieldErrorCode::InvalidEmail, message);
+        }
+        self
+    }
+
+    /// `value`, if any, must look like a phone number.
+    pub fn phone(&mut self, field: &str, value: Option<&str>) -> &mut Self {
+        if value.is_some_and(|value| !is_phone(value)) {
+            let message = format!("{} must be a valid phone number!", field);
+            self.add(field, FieldErrorCode::InvalidPhone, message);
+        }
+        self
+    }
+
+    /// `value`, if any, must be an IANA time zone name.
+    pub fn time_zone(&mut self, field: &str, value: Option<&str>) -> &mut Self {
+        if value.is_some_and(|value| value.parse::<Tz>().is_err()) {
+            let message = format!("{} must be an IANA time zone like Asia/Ho_Chi_Minh!", field);
+            self.add(field, FieldErrorCode::InvalidTimeZone, message);
+        }
+        self
+    }
+
+    /// `value`, if any, must not be negative.
+    pub fn non_negative(&mut self, field: &str, value: Option<i64>) -> &mut Self {
+        if value.is_some_and(|value| value < 0) {
+            let message = format!("{} must not be negative!", field);
+            self.add(field, FieldErrorCode::Negative, message);
+        }
+        self
+    }
+
+    /// `value`, if any, must be greater than 0.
+    pub fn positive(&mut self, field: &str, value: Option<i64>) -> &mut Self {
+        if value.is_some_and(|value| value <= 0) {
+            let message = format!("{} must be greater than 0!", field);
+            self.add(field, FieldErrorCode::NotPositive, message);
+        }
+        self
+    }
+
+    /// `value`, if any, must not be greater than `max`.
+    pub fn at_most(&mut self, field: &str, value: Option<i64>, max: i64) -> &mut Self {
+        if value.is_some_and(|value| value > max) {
+            let message = format!("{} must not be greater than {}!", field, max);
+            self.add(field, FieldErrorCode::TooLarge, message);
+        }
+        self
+    }
+
+    /// `value` must be a password of 8 to 72 bytes, with letters and digits,
+    /// other than `username`.
+    pub fn password(&mut self, field: &str, value: &str, username: Option<&str>) -> &mut Self {
+        let (code, message) = if value.chars().count() < MIN_PASSWORD_LENGTH {
+            let message = format!(
+                "{} must have at least {} characters!",
+                field, MIN_PASSWORD_LENGTH
+            );
+            (FieldErrorCode::TooShort, message)
+        } else if value.len() > MAX_PASSWORD_BYTES {
+            let message = format!(
+                "{} must not be longer than {} bytes!",
+                field, MAX_PASSWORD_BYTES
+            );
+            (FieldErrorCode::TooLong, message)
+        } else if !value.chars().any(char::is_alphabetic)
+            || !value.chars().any(|c| c.is_ascii_digit())
+        {
+            let message = format!("{} must contain both letters and digits!", field);
+            (FieldErrorCode::WeakPassword, message)
+        } else if username.is_some_and(|username| value.eq_ignore_ascii_case(username)) {
+            let message = format!("{} must not be the username!", field);
+            (FieldErrorCode::WeakPassword, message)
+        } else {
+            return self;
+        };
+        self.add(field, code, message);
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::Validation(std::mem::take(&mut self.errors)))
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !value.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+fn is_phone(value: &str) -> bool {
+    let number = value.strip_prefix('+').unwrap_or(value);
+    let valid_chars = number
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == ' ' || c == '-' || c == '.');
+    let digits = number.chars().filter(char::is_ascii_digit).count();
+    valid_chars && (MIN_PHONE_DIGITS..=MAX_PHONE_DIGITS).contains(&digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(validator: &mut Validator) -> Vec<(String, FieldErrorCode)> {
+        match validator.finish() {
+            Ok(()) => vec![],
+            Err(AppError::Validation(errors)) => errors
+                .into_iter()
+                .map(|error| (error.field, error.code))
+                .collect(),
+            Err(error) => panic!("unexpected error {:?}", error),
+        }
+    }
+
+    #[test]
+    fn valid_input_passes() {
+        let mut validator = Validator::new();
+        validator
+            .required("name", Some("Salon"))
+            .email("email", Some("owner@salon.vn"))
+            .phone("phone", Some("+84 912-345-678"))
+            .time_zone("timeZone", Some("Asia/Ho_Chi_Minh"))
+            .non_negative("price", Some(0))
+            .positive("quantity", Some(1))
+            .at_most("durationMinutes", Some(60), 60)
+            .password("password", "secret123", Some("owner"));
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn missing_optional_values_pass() {
+        let mut validator = Validator::new();
+        validator
+            .email("email", None)
+            .phone("phone", None)
+            .time_zone("timeZone", None)
+            .non_negative("price", None)
+            .positive("quantity", None)
+            .at_most("durationMinutes", None, 60);
+        assert!(validator.finish().is_ok());
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let mut validator = Validator::new();
+        validator
+            .required("name", Some("  "))
+            .required("address", None)
+            .email("email", Some("owner@salon"))
+            .phone("phone", Some("12-34"))
+            .time_zone("timeZone", Some("GMT+7"))
+            .non_negative("price", Some(-1))
+            .positive("quantity", Some(0))
+            .at_most("durationMinutes", Some(61), 60);
+        assert_eq!(
+            codes(&mut validator),
+            vec![
+                ("name".to_string(), FieldErrorCode::Required),
+                ("address".to_string(), FieldErrorCode::Required),
+                ("email".to_string(), FieldErrorCode::InvalidEmail),
+                ("phone".to_string(), FieldErrorCode::InvalidPhone),
+                ("timeZone".to_string(), FieldErrorCode::InvalidTimeZone),
+                ("price".to_string(), FieldErrorCode::Negative),
+                ("quantity".to_string(), FieldErrorCode::NotPositive),
+                ("durationMinutes".to_string(), FieldErrorCode::TooLarge),
+            ]
+        );
+    }
+
+    #[test]
+    fn weak_passwords_are_rejected() {
+        let cases = [
+            ("short1", FieldErrorCode::TooShort),
+            ("a1234567".repeat(10).as_str(), FieldErrorCode::TooLong),
+            ("onlyletters", FieldErrorCode::WeakPassword),
+            ("12345678", FieldErrorCode::WeakPassword),
+            ("Customer1", FieldErrorCode::WeakPassword),
+        ]
+        .map(|(password, code)| (password.to_string(), code));
+        for (password, code) in cases {
+            let mut validator = Validator::new();
+            validator.password("password", &password, Some("customer1"));
+            assert_eq!(
+                codes(&mut validator),
+                vec![("password".to_string(), code)],
+                "{}",
+                password
+            );
+        }
+    }
+
+    #[test]
+    fn finish_clears_errors() {
+        let mut validator = Validator::new();
+        validator.required("name", None);
+        assert!(validator.finish().is_err());
+        assert!(validator.finish().is_ok());
+    }
+}